@@ -0,0 +1,230 @@
+// Benchmarks for the work every authenticated request does: decrypting the session
+// cookie, decoding stateless claims and running check_authenticated in both session
+// modes. The database benchmarks need DATABASE_URL pointing at a Postgres the
+// migrations can run against, and are skipped without it.
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, Request},
+    middleware,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use axum_extra::extract::cookie::{Cookie, Key, PrivateCookieJar};
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use reqwest::Client as ReqwestClient;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+use tracing_subscriber::{reload, Registry};
+
+use oauth_axum::config::reload::{log_filter, LiveConfig};
+use oauth_axum::config::settings::SessionMode;
+use oauth_axum::config::Settings;
+use oauth_axum::handlers::UserProfile;
+use oauth_axum::middleware::check_authenticated;
+use oauth_axum::oauth::GoogleKeys;
+use oauth_axum::services::pool_metrics::PoolMetrics;
+use oauth_axum::services::post_login::PostLoginHooks;
+use oauth_axum::services::stateless_session::SessionClaims;
+use oauth_axum::services::{Alerts, LoginThrottle, Maintenance, RateLimiter, RevokedSessions};
+use oauth_axum::state::AppState;
+
+const SESSION_ID: &str = "bench-session-0123456789abcdef0123456789abcdef";
+
+fn app_state(db: PgPool, session_mode: SessionMode) -> AppState {
+    let mut settings = Settings::from_env();
+    settings.session_mode = session_mode;
+
+    let ctx = ReqwestClient::new();
+    let alerts = Alerts::new(ctx.clone(), &[]);
+    let (_, log_filter_handle) = reload::Layer::<_, Registry>::new(log_filter());
+
+    AppState {
+        db,
+        ctx,
+        key: Key::generate(),
+        settings: Arc::new(settings),
+        throttle: LoginThrottle::new(
+            alerts.clone(),
+            5,
+            StdDuration::from_secs(1),
+            StdDuration::from_secs(900),
+        ),
+        geoip: None,
+        google_keys: GoogleKeys::new(),
+        maintenance: Maintenance::new(false),
+        csp_report_limiter: RateLimiter::new(60, StdDuration::from_secs(60)),
+        live_config: LiveConfig::new(Vec::new(), log_filter_handle),
+        alerts,
+        revoked_sessions: RevokedSessions::default(),
+        pool_metrics: PoolMetrics::default(),
+        post_login_hooks: PostLoginHooks::default(),
+    }
+}
+
+// A protected route behind the real middleware
+fn protected_app(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(|| async { "ok" }))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            check_authenticated,
+        ))
+        .with_state(state)
+}
+
+// Cookie header carrying an encrypted session cookie, as a browser would send it
+fn cookie_header(state: &AppState, value: String) -> String {
+    let cookie = Cookie::new(state.settings.session_cookie_name.clone(), value);
+    let response = PrivateCookieJar::new(state.key.clone())
+        .add(cookie)
+        .into_response();
+    let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+fn request(cookie: &str) -> Request<Body> {
+    let mut req = Request::get("/")
+        .header(header::COOKIE, cookie)
+        .header(header::USER_AGENT, "criterion")
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    req
+}
+
+fn stateless_claims() -> String {
+    SessionClaims {
+        sid: SESSION_ID.to_string(),
+        uid: 1,
+        roles: vec!["user".to_string()],
+        exp: Utc::now().timestamp() + 3600,
+        csrf_token: "0123456789abcdef0123456789abcdef".to_string(),
+        fingerprint: None,
+    }
+    .encode()
+}
+
+fn lazy_pool() -> PgPool {
+    // Never connects unless a query runs, which stateless checks don't do
+    PgPoolOptions::new()
+        .connect_lazy("postgres://bench@localhost/bench")
+        .unwrap()
+}
+
+fn cookie_decryption(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let state = app_state(lazy_pool(), SessionMode::Stateless);
+    let name = state.settings.session_cookie_name.clone();
+
+    let mut headers = HeaderMap::new();
+    let cookie = cookie_header(&state, stateless_claims());
+    headers.insert(header::COOKIE, cookie.parse().unwrap());
+
+    c.bench_function("cookie_decryption", |b| {
+        b.iter(|| {
+            PrivateCookieJar::from_headers(&headers, state.key.clone())
+                .get(&name)
+                .unwrap()
+        })
+    });
+}
+
+fn stateless_decode(c: &mut Criterion) {
+    let claims = stateless_claims();
+
+    c.bench_function("stateless_claims_decode", |b| {
+        b.iter(|| SessionClaims::decode(&claims).unwrap())
+    });
+}
+
+fn check_authenticated_stateless(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let state = app_state(lazy_pool(), SessionMode::Stateless);
+    let cookie = cookie_header(&state, stateless_claims());
+    let app = protected_app(state);
+
+    c.bench_function("check_authenticated/stateless", |b| {
+        b.to_async(&runtime)
+            .iter(|| app.clone().oneshot(request(&cookie)))
+    });
+}
+
+fn database_sessions(c: &mut Criterion) {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set; skipping the database session benchmarks");
+        return;
+    };
+
+    let runtime = Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let db = runtime.block_on(async {
+        let db = PgPool::connect(&database_url).await.unwrap();
+        oauth_axum::services::schema::run_migrations(&db, StdDuration::from_secs(60))
+            .await
+            .unwrap();
+
+        let (user_id,): (i32,) = sqlx::query_as(
+            "INSERT INTO users (email, display_name) VALUES ('bench@example.com', 'Bench User')
+             ON CONFLICT (email) DO UPDATE SET deleted_at = NULL
+             RETURNING id",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO sessions (user_id, session_id, expires_at)
+             VALUES ($1, $2, NOW() + INTERVAL '1 hour')
+             ON CONFLICT (session_id) DO UPDATE SET expires_at = excluded.expires_at",
+        )
+        .bind(user_id)
+        .bind(SESSION_ID)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        db
+    });
+
+    let state = app_state(db.clone(), SessionMode::Database);
+    let cookie = cookie_header(&state, SESSION_ID.to_string());
+    let app = protected_app(state);
+
+    c.bench_function("check_authenticated/database", |b| {
+        b.to_async(&runtime)
+            .iter(|| app.clone().oneshot(request(&cookie)))
+    });
+
+    c.bench_function("session_lookup/database", |b| {
+        b.to_async(&runtime)
+            .iter(|| UserProfile::load(&db, SESSION_ID, None))
+    });
+
+    runtime.block_on(async {
+        sqlx::query("DELETE FROM sessions WHERE session_id = $1")
+            .bind(SESSION_ID)
+            .execute(&db)
+            .await
+            .unwrap();
+    });
+}
+
+criterion_group!(
+    benches,
+    cookie_decryption,
+    stateless_decode,
+    check_authenticated_stateless,
+    database_sessions
+);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+// Generates the gRPC session service from proto/session.proto
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // sqlx::migrate! embeds the migrations, so a new one must trigger a rebuild
+    println!("cargo:rerun-if-changed=migrations");
+
+    // Bundled protoc, so building doesn't need one installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("proto/session.proto")?;
+
+    Ok(())
+}
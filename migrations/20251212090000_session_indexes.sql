@@ -0,0 +1,6 @@
+-- Session lookups happen on every authenticated request; a session id must
+-- never resolve to more than one row
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sessions_session_id ON sessions (session_id);
+
+-- Used by the background pruner to find expired sessions
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions (expires_at);
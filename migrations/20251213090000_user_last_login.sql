@@ -0,0 +1,6 @@
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS last_login_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS last_login_provider VARCHAR(32);
+
+-- Supports inactive-account queries such as "no login in the last 90 days"
+CREATE INDEX IF NOT EXISTS idx_users_last_login_at ON users (last_login_at);
@@ -0,0 +1,15 @@
+ALTER TABLE sessions
+    ADD COLUMN IF NOT EXISTS ip_address VARCHAR(45),
+    ADD COLUMN IF NOT EXISTS user_agent TEXT;
+
+-- Devices (IP address and user agent combinations) each user has signed in from
+CREATE TABLE IF NOT EXISTS user_devices (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    user_agent TEXT NOT NULL DEFAULT '',
+    first_seen_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, ip_address, user_agent),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Hash of the client IP range and/or user agent a session is bound to
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS fingerprint VARCHAR(64);
@@ -0,0 +1,5 @@
+-- Token required by state-changing form posts such as logout
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS csrf_token VARCHAR(64);
+
+-- Sessions from before the column existed get a token too, so their forms keep working
+UPDATE sessions SET csrf_token = md5(random()::text || id::text) WHERE csrf_token IS NULL;
@@ -0,0 +1,5 @@
+-- Disposable email domains an admin has explicitly allowed
+CREATE TABLE IF NOT EXISTS email_domain_overrides (
+    domain VARCHAR(255) PRIMARY KEY,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
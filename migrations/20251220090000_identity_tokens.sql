@@ -0,0 +1,6 @@
+-- Provider tokens per identity so granted API scopes can be used after login
+ALTER TABLE user_identities
+    ADD COLUMN IF NOT EXISTS access_token TEXT,
+    ADD COLUMN IF NOT EXISTS refresh_token TEXT,
+    ADD COLUMN IF NOT EXISTS token_expires_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS scopes TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,9 @@
+-- Daily login counters per provider for the admin statistics
+CREATE TABLE IF NOT EXISTS login_stats (
+    day DATE NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    logins INT NOT NULL DEFAULT 0,
+    signups INT NOT NULL DEFAULT 0,
+    failures INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, provider)
+);
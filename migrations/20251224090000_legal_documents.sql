@@ -0,0 +1,18 @@
+-- Versioned terms of service / privacy policy and who accepted which version
+CREATE TABLE IF NOT EXISTS legal_documents (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR(32) NOT NULL,
+    version VARCHAR(64) NOT NULL,
+    content TEXT NOT NULL,
+    published_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (kind, version)
+);
+
+CREATE TABLE IF NOT EXISTS legal_acceptances (
+    user_id INT NOT NULL,
+    document_id INT NOT NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, document_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (document_id) REFERENCES legal_documents(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Onboarding progress; existing users are treated as already onboarded
+ALTER TABLE users ADD COLUMN IF NOT EXISTS onboarding_step VARCHAR(32) NOT NULL DEFAULT 'done';
@@ -0,0 +1,23 @@
+-- Time-boxed tokens a user hands to support for read-only access to their account
+CREATE TABLE IF NOT EXISTS support_access_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_support_access_tokens_user_id ON support_access_tokens (user_id);
+
+-- Every use of a support token, kept for auditing
+CREATE TABLE IF NOT EXISTS support_access_log (
+    id SERIAL PRIMARY KEY,
+    token_id INT NOT NULL,
+    agent_user_id INT NOT NULL,
+    ip_address VARCHAR(45),
+    accessed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (token_id) REFERENCES support_access_tokens(id) ON DELETE CASCADE
+);
@@ -0,0 +1,47 @@
+-- Append-only log of auth state changes. There is no foreign key so events
+-- outlive purged users.
+CREATE TABLE IF NOT EXISTS auth_events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    kind VARCHAR(32) NOT NULL,
+    provider VARCHAR(32),
+    detail VARCHAR(255),
+    ip_address VARCHAR(45),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_events_user_id ON auth_events (user_id, id);
+CREATE INDEX IF NOT EXISTS idx_auth_events_kind ON auth_events (kind, id);
+
+CREATE OR REPLACE FUNCTION auth_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'auth_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS auth_events_append_only ON auth_events;
+CREATE TRIGGER auth_events_append_only
+    BEFORE UPDATE OR DELETE ON auth_events
+    FOR EACH ROW EXECUTE FUNCTION auth_events_append_only();
+
+-- Roles are granted directly in the database, so role changes are captured here
+CREATE OR REPLACE FUNCTION record_role_changes() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO auth_events (user_id, kind, detail)
+    SELECT NEW.id, 'role_granted', role
+    FROM unnest(NEW.roles) AS role
+    WHERE NOT role = ANY(OLD.roles);
+
+    INSERT INTO auth_events (user_id, kind, detail)
+    SELECT NEW.id, 'role_revoked', role
+    FROM unnest(OLD.roles) AS role
+    WHERE NOT role = ANY(NEW.roles);
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS users_role_changes ON users;
+CREATE TRIGGER users_role_changes
+    AFTER UPDATE OF roles ON users
+    FOR EACH ROW EXECUTE FUNCTION record_role_changes();
@@ -0,0 +1,4 @@
+-- Allow several sessions per user, one per login
+ALTER TABLE sessions DROP CONSTRAINT IF EXISTS sessions_user_id_key;
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions (user_id);
@@ -0,0 +1,19 @@
+-- Guest sessions are session rows without a user until the guest signs in
+ALTER TABLE sessions ALTER COLUMN user_id DROP NOT NULL;
+
+-- Preferences of a guest, moved to user_preferences when the guest signs in
+CREATE TABLE IF NOT EXISTS session_preferences (
+    session_id INT NOT NULL,
+    key VARCHAR(64) NOT NULL,
+    value VARCHAR(1024) NOT NULL,
+    PRIMARY KEY (session_id, key),
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id INT NOT NULL,
+    key VARCHAR(64) NOT NULL,
+    value VARCHAR(1024) NOT NULL,
+    PRIMARY KEY (user_id, key),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Emails admins have invited to create an account when registration is restricted
+CREATE TABLE IF NOT EXISTS invitations (
+    email VARCHAR(255) PRIMARY KEY,
+    invited_by INT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    accepted_at TIMESTAMP WITH TIME ZONE
+);
+
+-- Unknown users who tried to sign in while registration was restricted
+CREATE TABLE IF NOT EXISTS access_requests (
+    email VARCHAR(255) PRIMARY KEY,
+    provider VARCHAR(32) NOT NULL,
+    display_name VARCHAR(255),
+    requested_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
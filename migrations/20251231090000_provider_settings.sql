@@ -0,0 +1,6 @@
+-- Providers an admin has switched off at runtime; a missing row means enabled
+CREATE TABLE IF NOT EXISTS provider_settings (
+    provider VARCHAR(32) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,21 @@
+-- Bulk user imports run as background jobs
+CREATE TABLE IF NOT EXISTS user_imports (
+    id SERIAL PRIMARY KEY,
+    created_by INT,
+    -- pending, running, finished or failed
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    total_rows INT NOT NULL,
+    created_rows INT NOT NULL DEFAULT 0,
+    failed_rows INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+-- Rows that could not be imported; row numbers start at 1 for the first data row
+CREATE TABLE IF NOT EXISTS user_import_errors (
+    import_id INT NOT NULL REFERENCES user_imports(id) ON DELETE CASCADE,
+    row_number INT NOT NULL,
+    email VARCHAR(255),
+    error TEXT NOT NULL,
+    PRIMARY KEY (import_id, row_number)
+);
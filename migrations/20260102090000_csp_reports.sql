@@ -0,0 +1,16 @@
+-- Content-Security-Policy violations reported by browsers, aggregated per
+-- page, directive and blocked resource so noisy pages can't grow the table
+CREATE TABLE IF NOT EXISTS csp_reports (
+    id SERIAL PRIMARY KEY,
+    document_uri TEXT NOT NULL,
+    directive TEXT NOT NULL,
+    blocked_uri TEXT NOT NULL,
+    source_file TEXT,
+    line_number INT,
+    sample TEXT,
+    user_agent TEXT,
+    count INT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (document_uri, directive, blocked_uri)
+);
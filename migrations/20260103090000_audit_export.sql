@@ -0,0 +1,7 @@
+-- How far the audit exporter has forwarded auth_events. A single row, locked
+-- while a batch is sent so replicas never export the same events twice.
+CREATE TABLE IF NOT EXISTS audit_export_cursor (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_event_id BIGINT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- Last auth event checked for admin role grants to alert on, locked while
+-- alerts are raised so each grant is reported by one replica only
+CREATE TABLE IF NOT EXISTS alert_cursor (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_event_id BIGINT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,20 @@
+-- Tell every app instance when a session is deleted before it expires, so
+-- stateless sessions are revoked on all replicas. Only a hash of the session
+-- id is sent; anyone allowed to LISTEN must not learn live session ids.
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS trigger AS $$
+BEGIN
+    IF OLD.expires_at > NOW() THEN
+        PERFORM pg_notify('oauth_axum_invalidation', json_build_object(
+            'kind', 'session_revoked',
+            'session', encode(sha256(convert_to(OLD.session_id, 'UTF8')), 'hex'),
+            'expires_at', OLD.expires_at
+        )::text);
+    END IF;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sessions_notify_revoked ON sessions;
+CREATE TRIGGER sessions_notify_revoked
+    AFTER DELETE ON sessions
+    FOR EACH ROW EXECUTE FUNCTION notify_session_revoked();
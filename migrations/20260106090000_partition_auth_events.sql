@@ -0,0 +1,49 @@
+-- Partition auth_events by month so old months can be dropped whole instead of
+-- deleting rows. The existing table becomes the first partition, named after the
+-- month it ends before (auth_events_before_yYYYYmMM); the app creates the monthly
+-- partitions (auth_events_yYYYYmMM) ahead of time. Requires PostgreSQL 13+.
+DO $$
+DECLARE
+    next_month TIMESTAMP WITH TIME ZONE := date_trunc('month', NOW()) + INTERVAL '1 month';
+    legacy TEXT := 'auth_events_before_' || to_char(next_month, '"y"YYYY"m"MM');
+BEGIN
+    DROP TRIGGER IF EXISTS auth_events_append_only ON auth_events;
+    ALTER TABLE auth_events RENAME TO auth_events_unpartitioned;
+    ALTER INDEX idx_auth_events_user_id RENAME TO idx_auth_events_unpartitioned_user_id;
+    ALTER INDEX idx_auth_events_kind RENAME TO idx_auth_events_unpartitioned_kind;
+
+    CREATE TABLE auth_events (
+        id BIGINT NOT NULL DEFAULT nextval('auth_events_id_seq'),
+        user_id INT NOT NULL,
+        kind VARCHAR(32) NOT NULL,
+        provider VARCHAR(32),
+        detail VARCHAR(255),
+        ip_address VARCHAR(45),
+        occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (id, occurred_at)
+    ) PARTITION BY RANGE (occurred_at);
+    ALTER SEQUENCE auth_events_id_seq OWNED BY auth_events.id;
+
+    -- The check lets ATTACH skip scanning the old rows
+    EXECUTE format(
+        'ALTER TABLE auth_events_unpartitioned ADD CONSTRAINT auth_events_before_bound CHECK (occurred_at < %L)',
+        next_month
+    );
+    EXECUTE format(
+        'ALTER TABLE auth_events ATTACH PARTITION auth_events_unpartitioned FOR VALUES FROM (MINVALUE) TO (%L)',
+        next_month
+    );
+    EXECUTE format('ALTER TABLE auth_events_unpartitioned RENAME TO %I', legacy);
+
+    -- Catches events for months whose partition doesn't exist yet
+    CREATE TABLE auth_events_default PARTITION OF auth_events DEFAULT;
+END;
+$$;
+
+CREATE INDEX IF NOT EXISTS idx_auth_events_user_id ON auth_events (user_id, id);
+CREATE INDEX IF NOT EXISTS idx_auth_events_kind ON auth_events (kind, id);
+
+-- Dropping a partition is not a DELETE, so retention still works
+CREATE TRIGGER auth_events_append_only
+    BEFORE UPDATE OR DELETE ON auth_events
+    FOR EACH ROW EXECUTE FUNCTION auth_events_append_only();
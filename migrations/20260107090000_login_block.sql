@@ -0,0 +1,2 @@
+-- Set by an admin force-logout to keep the user from signing in again for a while
+ALTER TABLE users ADD COLUMN IF NOT EXISTS login_blocked_until TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,75 @@
+use axum_extra::extract::cookie::Key;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::env;
+
+// Development fallback; anyone who has read this repository can decrypt cookies made with it
+const DEFAULT_COOKIE_KEY: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+// Key::from needs at least 64 bytes of key material
+const KEY_LENGTH: usize = 64;
+
+// HKDF doesn't add entropy, so a short passphrase gives a guessable key
+const MIN_PASSPHRASE_LENGTH: usize = 32;
+
+// Load the cookie encryption key from the environment:
+// - COOKIE_KEY_PASSPHRASE (+ optional COOKIE_KEY_SALT) is stretched with HKDF-SHA256
+// - COOKIE_KEY=base64:<...> is decoded from base64
+// - any other COOKIE_KEY is used as raw bytes
+// Release builds refuse to start without a key or with the development default.
+pub fn cookie_key() -> Key {
+    if let Ok(passphrase) = env::var("COOKIE_KEY_PASSPHRASE") {
+        if passphrase.trim().is_empty() {
+            panic!("COOKIE_KEY_PASSPHRASE is set but empty");
+        }
+        if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+            if !cfg!(debug_assertions) {
+                panic!(
+                    "COOKIE_KEY_PASSPHRASE must be at least {} characters in release builds",
+                    MIN_PASSPHRASE_LENGTH
+                );
+            }
+            tracing::warn!(
+                "COOKIE_KEY_PASSPHRASE is shorter than {} characters",
+                MIN_PASSPHRASE_LENGTH
+            );
+        }
+
+        let salt = env::var("COOKIE_KEY_SALT").unwrap_or_default();
+        return derive_key(&passphrase, &salt);
+    }
+
+    let value = match env::var("COOKIE_KEY") {
+        Ok(value) if value != DEFAULT_COOKIE_KEY => value,
+        _ if cfg!(debug_assertions) => {
+            tracing::warn!("COOKIE_KEY is not set; using the insecure development key");
+            DEFAULT_COOKIE_KEY.to_string()
+        }
+        _ => panic!(
+            "COOKIE_KEY or COOKIE_KEY_PASSPHRASE must be set to a secret value in release builds"
+        ),
+    };
+
+    let bytes = match value.strip_prefix("base64:") {
+        Some(encoded) => STANDARD
+            .decode(encoded.trim())
+            .expect("COOKIE_KEY is not valid base64"),
+        None => value.into_bytes(),
+    };
+
+    if bytes.len() < KEY_LENGTH {
+        panic!("COOKIE_KEY must be at least {} bytes", KEY_LENGTH);
+    }
+
+    Key::from(&bytes)
+}
+
+fn derive_key(passphrase: &str, salt: &str) -> Key {
+    let mut okm = [0u8; KEY_LENGTH];
+    Hkdf::<Sha256>::new(Some(salt.as_bytes()), passphrase.as_bytes())
+        .expand(b"oauth_axum cookie key", &mut okm)
+        .expect("64 bytes is a valid HKDF-SHA256 output length");
+
+    Key::from(&okm)
+}
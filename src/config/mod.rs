@@ -1,2 +1,7 @@
+pub mod cookie_key;
+pub mod reload;
 pub mod router;
+pub mod settings;
+pub use cookie_key::cookie_key;
 pub use router::*;
+pub use settings::Settings;
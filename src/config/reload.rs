@@ -0,0 +1,170 @@
+use axum::http::HeaderValue;
+use serde::Serialize;
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::Settings;
+use crate::state::AppState;
+
+// Applied on SIGUSR1 to see what the app is doing during an incident
+const VERBOSE_LOG_FILTER: &str =
+    "oauth_axum=trace,tower_http=debug,axum::rejection=trace,sqlx::query=info";
+
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+// RUST_LOG, or the default filter when it is unset or invalid
+pub fn log_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "oauth_axum=debug,axum::rejection=trace,sqlx::query=warn".into())
+}
+
+// Settings that take effect without a restart. Rate limits live in the
+// limiters themselves; provider toggles are read from the database on every login.
+#[derive(Clone)]
+pub struct LiveConfig {
+    cors_origins: Arc<RwLock<Vec<HeaderValue>>>,
+    log_filter: LogFilterHandle,
+}
+
+impl LiveConfig {
+    pub fn new(cors_origins: Vec<HeaderValue>, log_filter: LogFilterHandle) -> Self {
+        Self {
+            cors_origins: Arc::new(RwLock::new(cors_origins)),
+            log_filter,
+        }
+    }
+
+    pub fn cors_allows(&self, origin: &HeaderValue) -> bool {
+        self.cors_origins.read().unwrap().contains(origin)
+    }
+
+    pub fn current_log_filter(&self) -> String {
+        self.log_filter
+            .with_current(|filter| filter.to_string())
+            .unwrap_or_default()
+    }
+
+    // Replace the log filter until the next reload or restart
+    pub fn set_log_filter(&self, directives: &str) -> Result<String, String> {
+        let filter =
+            EnvFilter::try_new(directives).map_err(|e| format!("Invalid log filter: {}", e))?;
+        self.apply_log_filter(filter)
+    }
+
+    fn apply_log_filter(&self, filter: EnvFilter) -> Result<String, String> {
+        let description = filter.to_string();
+        self.log_filter
+            .reload(filter)
+            .map_err(|e| format!("Failed to update the log filter: {}", e))?;
+        Ok(description)
+    }
+}
+
+pub fn parse_cors_origins(origins: &[String]) -> Result<Vec<HeaderValue>, String> {
+    origins
+        .iter()
+        .map(|origin| {
+            origin
+                .parse::<HeaderValue>()
+                .map_err(|_| format!("Invalid CORS origin {}", origin))
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReloadedConfig {
+    pub cors_allowed_origins: Vec<String>,
+    pub login_throttle_free_attempts: u32,
+    pub login_throttle_base_delay_secs: u64,
+    pub login_throttle_max_delay_secs: u64,
+    pub csp_report_limit_per_minute: u32,
+    pub log_filter: String,
+}
+
+// Re-read .env and apply the reloadable settings. Sessions, listeners and the
+// database pool are untouched; everything else still needs a restart.
+pub fn reload(state: &AppState) -> Result<ReloadedConfig, String> {
+    // Variables set in .env win over the ones the process was started with,
+    // otherwise edits to the file would never be seen
+    if let Ok(vars) = dotenv::dotenv_iter() {
+        for var in vars {
+            let (name, value) = var.map_err(|e| format!("Failed to read .env: {}", e))?;
+            env::set_var(name, value);
+        }
+    }
+
+    // Settings::from_env panics on invalid values; keep the running configuration then
+    let settings = std::panic::catch_unwind(Settings::from_env)
+        .map_err(|_| "Invalid configuration".to_string())?;
+    let cors_origins = parse_cors_origins(&settings.cors_allowed_origins)?;
+    let filter_description = state.live_config.apply_log_filter(log_filter())?;
+    *state.live_config.cors_origins.write().unwrap() = cors_origins;
+    state.throttle.set_limits(
+        settings.login_throttle_free_attempts,
+        Duration::from_secs(settings.login_throttle_base_delay_secs),
+        Duration::from_secs(settings.login_throttle_max_delay_secs),
+    );
+    state
+        .csp_report_limiter
+        .set_limit(settings.csp_report_limit_per_minute);
+
+    Ok(ReloadedConfig {
+        cors_allowed_origins: settings.cors_allowed_origins,
+        login_throttle_free_attempts: settings.login_throttle_free_attempts,
+        login_throttle_base_delay_secs: settings.login_throttle_base_delay_secs,
+        login_throttle_max_delay_secs: settings.login_throttle_max_delay_secs,
+        csp_report_limit_per_minute: settings.csp_report_limit_per_minute,
+        log_filter: filter_description,
+    })
+}
+
+pub fn spawn_sighup_reloader(state: AppState) {
+    tokio::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+
+        while hangups.recv().await.is_some() {
+            match reload(&state) {
+                Ok(config) => tracing::info!("Configuration reloaded: {:?}", config),
+                Err(e) => tracing::error!("Failed to reload configuration: {}", e),
+            }
+        }
+    });
+}
+
+// SIGUSR1 switches to verbose logging, SIGUSR2 goes back to RUST_LOG
+pub fn spawn_log_level_switcher(live_config: LiveConfig) {
+    tokio::spawn(async move {
+        let (mut verbose, mut normal) = match (
+            signal(SignalKind::user_defined1()),
+            signal(SignalKind::user_defined2()),
+        ) {
+            (Ok(verbose), Ok(normal)) => (verbose, normal),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!("Failed to listen for SIGUSR1/SIGUSR2: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            let applied = tokio::select! {
+                Some(()) = verbose.recv() => live_config.set_log_filter(VERBOSE_LOG_FILTER),
+                Some(()) = normal.recv() => live_config.apply_log_filter(log_filter()),
+                else => return,
+            };
+
+            match applied {
+                Ok(filter) => tracing::warn!("Log filter set to {}", filter),
+                Err(e) => tracing::error!("Failed to switch the log filter: {}", e),
+            }
+        }
+    });
+}
@@ -1,51 +1,260 @@
-use axum::{middleware, routing::get, Extension, Router};
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, Method},
+    middleware,
+    routing::{delete, get, post, put},
+    Extension, Router,
+};
+use std::sync::Arc;
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowMethods, AllowOrigin, CorsLayer},
+    limit::RequestBodyLimitLayer,
+    services::ServeDir,
+};
 
+use crate::config::reload::LiveConfig;
+use crate::config::Settings;
 use crate::handlers::{
-    get_profile, google_callback, health_check, homepage, login_page, protected, twitter_callback,
-    twitter_login,
+    accept_terms, activity_page, admin_dashboard, admin_stats, allow_email_domain, calendar_events,
+    complete_onboarding_profile, connections_page, create_invitation, create_support_token,
+    create_tweet, csp_report, delete_invitation, delete_user, disable_maintenance,
+    disable_provider, disallow_email_domain, enable_maintenance, enable_provider, export_users,
+    find_identity_owner, force_logout_form, force_logout_user, get_import, get_log_filter,
+    get_preferences, get_profile, google_callback, google_grant_scopes, google_login,
+    google_one_tap, google_silent_login, health_check, homepage, import_users,
+    list_access_requests, list_auth_events, list_csp_reports, list_providers, list_user_identities,
+    list_users, login_page, logout_page, me, metrics, onboarding_page, protected,
+    publish_legal_document, put_preference, readiness_check, reload_config, restore_user,
+    revoke_connection_access, revoke_support_token, set_log_filter, start_guest,
+    support_access_page, support_account, terms_page, tweets_page, twitter_callback, twitter_login,
 };
-use crate::middleware::check_authenticated;
-use crate::oauth::{ClientIds, OAuthClients, PkceVerifiers};
-use crate::services::logout;
+use crate::middleware::{
+    access_log, check_authenticated, content_security_policy, deprecated_api, maintenance_mode,
+    report_errors, require_admin, require_client_cert, require_live_session, require_onboarding,
+    require_terms_accepted, restrict_admin_ips,
+};
+use crate::oauth::{OAuthClients, PkceVerifiers};
+use crate::services::post_login::{PostLoginHook, PostLoginHooks};
+use crate::services::{logout, refresh_session};
 use crate::state::AppState;
 
 pub fn init_router(
-    state: AppState,
+    mut state: AppState,
     oauth_clients: OAuthClients,
-    client_ids: ClientIds,
     pkce_verifiers: PkceVerifiers,
+    post_login_hooks: Vec<Arc<dyn PostLoginHook>>,
 ) -> Router {
+    state.post_login_hooks = PostLoginHooks::new(post_login_hooks);
+
     // Auth routes
     let auth_router = Router::new()
         .route("/auth/google_callback", get(google_callback))
         .route("/auth/twitter_callback", get(twitter_callback))
+        .route("/auth/google_login", get(google_login))
+        .route("/auth/google_silent", get(google_silent_login))
+        .route("/auth/google_one_tap", post(google_one_tap))
+        .route("/auth/google_scopes", get(google_grant_scopes))
         .route("/auth/twitter_login", get(twitter_login))
-        .route("/auth/logout", get(logout));
+        .route("/auth/guest", post(start_guest))
+        .route("/auth/refresh", post(refresh_session))
+        .route("/auth/logout", get(logout_page).post(logout));
 
     // Protected routes
     let protected_router = Router::new()
         .route("/", get(protected))
         .route("/profile", get(get_profile))
+        .route("/activity", get(activity_page))
+        .route("/tweets", get(tweets_page).post(create_tweet))
+        .route(
+            "/support-access",
+            get(support_access_page)
+                .post(create_support_token)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    require_live_session,
+                )),
+        )
+        .route("/connections", get(connections_page))
+        .route(
+            "/connections/:id/revoke",
+            post(revoke_connection_access).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_live_session,
+            )),
+        )
+        .route(
+            "/support-access/:id/revoke",
+            post(revoke_support_token).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_live_session,
+            )),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_onboarding,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_terms_accepted,
+        ))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             check_authenticated,
         ));
 
+    // Google API proxies; UserProfile rejects anonymous requests with 401
+    let google_router = Router::new().route("/calendar/events", get(calendar_events));
+
+    // Preferences of the signed-in user or the current guest
+    let preferences_router = Router::new()
+        .route("/", get(get_preferences))
+        .route("/:key", put(put_preference));
+
+    // Read-only account access for support agents holding a user's support token
+    let support_router = Router::new()
+        .route("/account", get(support_account))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_live_session,
+        ));
+
+    // Admin routes; the client certificate, IP allowlist and live session are checked
+    // before the role
+    let admin_router = Router::new()
+        .route("/users", get(list_users))
+        .route("/users/export", get(export_users))
+        .route("/users/import", post(import_users))
+        .route("/users/import/:id", get(get_import))
+        .route("/users/:id", delete(delete_user))
+        .route("/users/:id/restore", post(restore_user))
+        .route("/users/:id/logout", post(force_logout_user))
+        .route("/users/:id/identities", get(list_user_identities))
+        .route(
+            "/identities/:provider/:provider_user_id",
+            get(find_identity_owner),
+        )
+        .route("/stats", get(admin_stats))
+        .route("/events", get(list_auth_events))
+        .route("/csp-reports", get(list_csp_reports))
+        .route("/access-requests", get(list_access_requests))
+        .route("/invitations", post(create_invitation))
+        .route("/invitations/:email", delete(delete_invitation))
+        .route("/providers", get(list_providers))
+        .route(
+            "/providers/:provider/enabled",
+            put(enable_provider).delete(disable_provider),
+        )
+        .route(
+            "/maintenance",
+            put(enable_maintenance).delete(disable_maintenance),
+        )
+        .route("/config/reload", post(reload_config))
+        .route("/log-filter", get(get_log_filter).put(set_log_filter))
+        .route("/legal-documents", post(publish_legal_document))
+        .route("/dashboard", get(admin_dashboard))
+        .route("/dashboard/force-logout", post(force_logout_form))
+        .route(
+            "/email-domains/:domain/allow",
+            put(allow_email_domain).delete(disallow_email_domain),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_live_session,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            restrict_admin_ips,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_client_cert,
+        ));
+
     // Public routes
     let public_router = Router::new()
         .route("/", get(homepage))
         .route("/login", get(login_page))
+        .route("/terms", get(terms_page).post(accept_terms))
+        .route(
+            "/onboarding",
+            get(onboarding_page).post(complete_onboarding_profile),
+        )
+        .route("/csp-report", post(csp_report))
         .route("/health", get(health_check))
-        .nest_service("/static", ServeDir::new("static"));
+        .route("/ready", get(readiness_check));
+
+    // Scraped by monitoring, which has no session; limited to the admin networks
+    let metrics_router =
+        Router::new()
+            .route("/metrics", get(metrics))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                restrict_admin_ips,
+            ));
+
+    // Size limit enforced by tower-http instead of axum's per-extractor default
+    let body_limit = state.settings.request_body_limit;
+    let cors = cors_layer(&state.settings, state.live_config.clone());
+
+    // JSON APIs, versioned so a breaking change can ship as /api/v2 next to v1. The
+    // unversioned /api paths stay as a deprecated alias of v1.
+    let api_v1 = Router::new()
+        .route("/me", get(me))
+        .nest("/admin", admin_router)
+        .nest("/google", google_router)
+        .nest("/support", support_router)
+        .nest("/preferences", preferences_router);
+    let legacy_api = api_v1.clone().layer(middleware::from_fn_with_state(
+        state.clone(),
+        deprecated_api,
+    ));
 
     Router::new()
-        .nest("/api", auth_router)
+        // OAuth redirect URIs are registered with the providers and stay unversioned
+        .nest("/api", auth_router.merge(legacy_api))
+        .nest("/api/v1", api_v1)
         .nest("/protected", protected_router)
+        .merge(metrics_router)
         .nest("/", public_router)
+        // Inside the CSP layer so the maintenance page gets a nonce
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_mode,
+        ))
+        // Rendered pages carry nonces; static files are served without the CSP header
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            content_security_policy,
+        ))
+        .nest_service("/static", ServeDir::new("static"))
         .layer(Extension(oauth_clients))
-        .layer(Extension(client_ids))
         .layer(Extension(pkce_verifiers))
-        .layer(CorsLayer::permissive())
+        .layer(cors)
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(body_limit))
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(report_errors))
+        // Outermost, so the latency and status cover every other layer
+        .layer(middleware::from_fn_with_state(state.clone(), access_log))
         .with_state(state)
 }
+
+// Cross-origin access is limited to the configured origins and methods. Origins
+// are looked up on every request so a configuration reload takes effect at once.
+fn cors_layer(settings: &Settings, live_config: LiveConfig) -> CorsLayer {
+    let methods = settings.cors_allowed_methods.iter().map(|method| {
+        method
+            .parse::<Method>()
+            .unwrap_or_else(|_| panic!("Invalid CORS method {}", method))
+    });
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            live_config.cors_allows(origin)
+        }))
+        .allow_methods(AllowMethods::list(methods))
+        .allow_headers([header::CONTENT_TYPE])
+        .allow_credentials(settings.cors_allow_credentials)
+}
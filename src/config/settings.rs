@@ -0,0 +1,770 @@
+use axum_extra::extract::cookie::SameSite;
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use oauth2::{url::Url, Scope};
+use std::collections::HashSet;
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::oauth::Provider;
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    // Public URL the app is served from, e.g. https://auth.example.com
+    pub base_url: String,
+    // Origins OAuth callbacks may come back to; the first one is the default
+    pub callback_origins: Vec<String>,
+    // Addresses the server accepts connections on, e.g. a public port plus a local admin socket
+    pub listen: Vec<ListenAddr>,
+    // Address of the gRPC session service for sibling backends; unset disables it
+    pub grpc_listen: Option<SocketAddr>,
+    // Bearer token gRPC callers must send, required when grpc_listen is set
+    pub grpc_auth_token: Option<String>,
+    // Name of the session cookie
+    pub session_cookie_name: String,
+    // Domain the session cookie is shared with, e.g. example.com for *.example.com
+    pub cookie_domain: Option<String>,
+    // Only send cookies over HTTPS
+    pub cookie_secure: bool,
+    pub cookie_same_site: SameSite,
+    // Where session state lives: a database row per session or the encrypted cookie
+    pub session_mode: SessionMode,
+    // Origins allowed to make cross-origin requests; empty means same-origin only
+    pub cors_allowed_origins: Vec<String>,
+    pub cors_allowed_methods: Vec<String>,
+    // Whether cross-origin requests may carry the session cookie
+    pub cors_allow_credentials: bool,
+    // Start with maintenance mode on; it can be toggled at runtime by admins
+    pub maintenance_mode: bool,
+    // Retry-After sent with the maintenance page, in seconds
+    pub maintenance_retry_after_secs: u64,
+    // Size of the database connection pool
+    pub database_max_connections: u32,
+    // How long a request waits for a free connection before failing with 503, in milliseconds
+    pub database_acquire_timeout_ms: u64,
+    // Server-side limit for every statement, in milliseconds (0 disables it)
+    pub database_statement_timeout_ms: u64,
+    // Statements slower than this are logged as warnings, in milliseconds
+    pub slow_query_threshold_ms: u64,
+    // Log the duration of tracing spans (database calls, login phases) when they close
+    pub log_span_timings: bool,
+    // How requests are logged: text, json or off
+    pub access_log_format: AccessLogFormat,
+    // Path prefixes left out of the access log, e.g. health checks
+    pub access_log_exclude: Vec<String>,
+    // Maximum accepted request body size in bytes
+    pub request_body_limit: usize,
+    // How long startup waits for another instance to finish migrating, in seconds
+    pub migration_lock_timeout_secs: u64,
+    // How often expired sessions are pruned, in seconds
+    pub session_prune_interval_secs: u64,
+    // Rows deleted per pruning statement
+    pub session_prune_batch_size: i64,
+    // Sessions expiring within this many seconds may be renewed
+    pub session_renewal_window_secs: i64,
+    // Lifetime of a renewed session, in seconds
+    pub session_renewal_secs: i64,
+    // No renewal extends a session past this many seconds after login
+    pub session_max_lifetime_secs: i64,
+    // Who may create an account by signing in
+    pub registration_mode: RegistrationMode,
+    // Emails or @domains allowed to register in allowlist mode (lowercase)
+    pub registration_allowlist: Vec<String>,
+    // Logins rejected before any account or session is touched
+    pub login_denylist: Vec<LoginDenyRule>,
+    // Asked to allow or deny every login; no external check when unset
+    pub login_policy_webhook: Option<String>,
+    // Let logins through when the policy webhook fails instead of rejecting them
+    pub login_policy_fail_open: bool,
+    // Where users land after logging in, unless one of their roles has its own page
+    pub login_redirect: String,
+    // (role, path) pairs; the first role the user has decides the landing page
+    pub role_login_redirects: Vec<(String, String)>,
+    // Where users land after logging out when no valid ?redirect= is given
+    pub logout_redirect: String,
+    // Absolute URLs or origins ?redirect= may point to after logout; local paths are always allowed
+    pub logout_redirect_allowlist: Vec<String>,
+    // Emails granted the admin role when their account is created (lowercase)
+    pub admin_emails: Vec<String>,
+    // Roles granted at every login to users whose provider claims match
+    pub claim_role_mappings: Vec<ClaimRoleMapping>,
+    // Claims used as the display name, in order; falls back to the provider's name
+    pub display_name_claims: Vec<ClaimPath>,
+    // Days an anonymous guest session lasts
+    pub guest_session_days: i32,
+    // Days a soft-deleted user is kept before being purged
+    pub user_retention_days: i32,
+    // How often soft-deleted users past the retention period are purged, in seconds
+    pub user_purge_interval_secs: u64,
+    // Months of auth events kept; older monthly partitions are dropped (0 keeps everything)
+    pub audit_retention_months: u32,
+    // How often provider tokens are checked for upcoming expiry, in seconds
+    pub token_refresh_interval_secs: u64,
+    // Tokens expiring within this many seconds are refreshed ahead of time
+    pub token_refresh_window_secs: u64,
+    // Failed logins allowed per IP/email before delays kick in
+    pub login_throttle_free_attempts: u32,
+    // First delay once throttled; doubles with every further failure
+    pub login_throttle_base_delay_secs: u64,
+    // Upper bound for the throttling delay
+    pub login_throttle_max_delay_secs: u64,
+    // Announced in the Sunset header of the deprecated unversioned /api routes
+    pub legacy_api_sunset: Option<DateTime<Utc>>,
+    // CSP violation reports accepted per client IP and minute
+    pub csp_report_limit_per_minute: u32,
+    // Reverse proxies whose X-Forwarded-For header is trusted
+    pub trusted_proxies: Vec<IpNet>,
+    // Networks allowed to reach the admin routes; empty means unrestricted
+    pub admin_allowed_networks: Vec<IpNet>,
+    // Require a client certificate verified by the TLS-terminating proxy for admin routes
+    pub admin_require_client_cert: bool,
+    // Header in which the proxy reports the verification result (must be SUCCESS)
+    pub client_cert_verify_header: String,
+    // Header carrying the certificate's fingerprint
+    pub client_cert_fingerprint_header: String,
+    // Accepted certificate fingerprints (lowercase hex); empty accepts any verified cert
+    pub admin_client_cert_fingerprints: Vec<String>,
+    // Where auth events are forwarded for a SIEM; export is off when unset
+    pub audit_export: Option<AuditSink>,
+    pub audit_export_format: AuditFormat,
+    // How often new auth events are forwarded, in seconds
+    pub audit_export_interval_secs: u64,
+    // (event type, webhook URL) pairs for security alerts; * routes every type
+    pub alert_webhooks: Vec<(String, String)>,
+    // Sentry DSN; errors and panics are only reported when set
+    pub sentry_dsn: Option<String>,
+    // Environment name attached to reported errors, e.g. production
+    pub sentry_environment: Option<String>,
+    // Path to a MaxMind City database; GeoIP enrichment is off when unset
+    pub geoip_database: Option<String>,
+    // Travel speed between consecutive logins above which a login is flagged
+    pub max_travel_speed_kmh: f64,
+    // Reject sessions used from a different IP range than they were created in
+    pub session_bind_ip_range: bool,
+    // Reject sessions used with a different user agent than they were created with
+    pub session_bind_user_agent: bool,
+    // Google Workspace domains allowed to log in; empty allows any Google account
+    pub google_allowed_domains: Vec<String>,
+    // Scopes and token options requested from each provider at login
+    pub google: ProviderSettings,
+    pub twitter: ProviderSettings,
+    // Offer Google One Tap sign-in on the home and login pages
+    pub google_one_tap: bool,
+    // Throwaway email domains rejected at login (bundled list plus custom entries)
+    pub disposable_email_domains: HashSet<String>,
+}
+
+// A claim name, optionally limited to one provider (google.hd)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimPath {
+    pub provider: Option<Provider>,
+    pub claim: String,
+}
+
+impl ClaimPath {
+    fn parse(value: &str) -> Self {
+        let scoped = value
+            .split_once('.')
+            .and_then(|(provider, claim)| Some((Provider::parse(provider)?, claim)));
+        match scoped {
+            Some((provider, claim)) => Self {
+                provider: Some(provider),
+                claim: claim.to_string(),
+            },
+            None => Self {
+                provider: None,
+                claim: value.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimRoleMapping {
+    pub claim: ClaimPath,
+    // Matches the claim itself, or any element when the claim is a list
+    pub value: String,
+    pub role: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoginDenyRule {
+    // None denies matching emails from every provider
+    pub provider: Option<Provider>,
+    // Lowercase email pattern where * matches anything
+    pub pattern: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditSink {
+    // RFC 5424 messages over UDP, to host:port
+    Syslog(String),
+    // Appended one event per line
+    File(PathBuf),
+    // POSTed in batches, one event per line
+    Http(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditFormat {
+    Json,
+    Cef,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionMode {
+    // Every request looks the session up in the database
+    Database,
+    // The user is read from the encrypted cookie; only high-risk routes check the database
+    Stateless,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationMode {
+    // Anyone signing in gets an account
+    Open,
+    // Only allowlisted addresses or domains, or invited emails
+    Allowlist,
+    // Only invited emails
+    InviteOnly,
+    // Nobody; accounts must be created beforehand (e.g. by a user import)
+    Closed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    Text,
+    Json,
+    Off,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProviderSettings {
+    pub scopes: Vec<String>,
+    // Ask for a refresh token (Google: access_type=offline, Twitter: offline.access)
+    pub offline_access: bool,
+    // Force the consent screen so Google issues a new refresh token (Twitter always prompts)
+    pub prompt_consent: bool,
+}
+
+impl ProviderSettings {
+    // Read <PREFIX>_SCOPES, <PREFIX>_OFFLINE_ACCESS and <PREFIX>_PROMPT_CONSENT
+    fn from_env(prefix: &str, default_scopes: &[&str]) -> Self {
+        let scopes = match env_list(&format!("{}_SCOPES", prefix)) {
+            scopes if scopes.is_empty() => default_scopes.iter().map(|s| s.to_string()).collect(),
+            scopes => scopes,
+        };
+
+        Self {
+            scopes,
+            offline_access: env_or(&format!("{}_OFFLINE_ACCESS", prefix), false),
+            prompt_consent: env_or(&format!("{}_PROMPT_CONSENT", prefix), false),
+        }
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = Scope> + '_ {
+        self.scopes.iter().map(|scope| Scope::new(scope.clone()))
+    }
+}
+
+impl Settings {
+    pub fn from_env() -> Self {
+        let base_url: String = env_or("BASE_URL", "http://localhost:8000".to_string());
+
+        // Every origin must be registered with the providers as a redirect URI
+        let callback_origins = match env_list("CALLBACK_ORIGINS") {
+            origins if origins.is_empty() => vec![base_url.clone()],
+            origins => origins,
+        }
+        .into_iter()
+        .map(|origin| match Url::parse(&origin) {
+            Ok(url) if url.has_host() && matches!(url.scheme(), "http" | "https") => {
+                url.origin().ascii_serialization()
+            }
+            _ => panic!(
+                "Invalid CALLBACK_ORIGINS entry {}, expected https://host[:port]",
+                origin
+            ),
+        })
+        .collect();
+
+        let cookie_domain = env::var("COOKIE_DOMAIN")
+            .ok()
+            .map(|domain| domain.trim_start_matches('.').to_lowercase());
+        if let Some(domain) = &cookie_domain {
+            validate_cookie_domain(&base_url, domain);
+        }
+
+        // Secure cookies (and the __Host- prefix, which requires them) by default over HTTPS.
+        // __Host- cookies can't carry a Domain, so shared cookies fall back to __Secure-.
+        let cookie_secure = env_or("COOKIE_SECURE", base_url.starts_with("https://"));
+        let default_cookie_name = match (cookie_secure, cookie_domain.is_some()) {
+            (true, false) => "__Host-sid",
+            (true, true) => "__Secure-sid",
+            (false, _) => "sid",
+        };
+        let session_cookie_name = env_or("SESSION_COOKIE_NAME", default_cookie_name.to_string());
+
+        if session_cookie_name.starts_with("__Host-") && !cookie_secure {
+            panic!("SESSION_COOKIE_NAME with the __Host- prefix requires COOKIE_SECURE=true");
+        }
+        if session_cookie_name.starts_with("__Host-") && cookie_domain.is_some() {
+            panic!(
+                "SESSION_COOKIE_NAME with the __Host- prefix can't be combined with COOKIE_DOMAIN"
+            );
+        }
+
+        let cookie_same_site = match env_or("COOKIE_SAME_SITE", "lax".to_string()).as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            other => panic!("Invalid COOKIE_SAME_SITE {}, expected lax or strict", other),
+        };
+
+        let session_mode = match env_or("SESSION_MODE", "database".to_string()).as_str() {
+            "database" => SessionMode::Database,
+            "stateless" => SessionMode::Stateless,
+            other => panic!(
+                "Invalid SESSION_MODE {}, expected database or stateless",
+                other
+            ),
+        };
+
+        let registration_mode = match env_or("REGISTRATION_MODE", "open".to_string()).as_str() {
+            "open" => RegistrationMode::Open,
+            "allowlist" => RegistrationMode::Allowlist,
+            "invite" => RegistrationMode::InviteOnly,
+            "closed" => RegistrationMode::Closed,
+            other => panic!(
+                "Invalid REGISTRATION_MODE {}, expected open, allowlist, invite or closed",
+                other
+            ),
+        };
+
+        let access_log_format = match env_or("ACCESS_LOG_FORMAT", "text".to_string()).as_str() {
+            "text" => AccessLogFormat::Text,
+            "json" => AccessLogFormat::Json,
+            "off" => AccessLogFormat::Off,
+            other => panic!(
+                "Invalid ACCESS_LOG_FORMAT {}, expected text, json or off",
+                other
+            ),
+        };
+
+        // role=/path entries, e.g. admin=/api/v1/admin/dashboard
+        let role_login_redirects = env_list("ROLE_LOGIN_REDIRECTS")
+            .into_iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((role, path)) if is_local_path(path) => (role.to_string(), path.to_string()),
+                _ => panic!(
+                    "Invalid ROLE_LOGIN_REDIRECTS entry {}, expected role=/path",
+                    entry
+                ),
+            })
+            .collect();
+
+        // claim=value:role entries, e.g. groups=eng-admins:admin or google.hd=example.com:staff
+        let claim_role_mappings = env_list("CLAIM_ROLE_MAPPINGS")
+            .into_iter()
+            .map(|entry| {
+                let parsed = entry.rsplit_once(':').and_then(|(matcher, role)| {
+                    let (claim, value) = matcher.split_once('=')?;
+                    Some((claim, value, role))
+                });
+                match parsed {
+                    Some((claim, value, role))
+                        if !claim.is_empty() && !value.is_empty() && !role.is_empty() =>
+                    {
+                        ClaimRoleMapping {
+                            claim: ClaimPath::parse(claim),
+                            value: value.to_string(),
+                            role: role.to_string(),
+                        }
+                    }
+                    _ => panic!(
+                        "Invalid CLAIM_ROLE_MAPPINGS entry {}, expected claim=value:role",
+                        entry
+                    ),
+                }
+            })
+            .collect();
+
+        // [provider:]pattern entries, e.g. *+test@example.com, @competitor.com or twitter:*
+        let login_denylist = env_list("LOGIN_DENYLIST")
+            .into_iter()
+            .map(|entry| {
+                let (provider, pattern) = match entry.split_once(':') {
+                    Some((provider, pattern)) => (
+                        Some(Provider::parse(provider).unwrap_or_else(|| {
+                            panic!("Invalid LOGIN_DENYLIST provider in {}", entry)
+                        })),
+                        pattern,
+                    ),
+                    None => (None, entry.as_str()),
+                };
+                // @domain is short for every address at the domain
+                let pattern = match pattern.strip_prefix('@') {
+                    Some(domain) => format!("*@{}", domain),
+                    None => pattern.to_string(),
+                };
+                LoginDenyRule {
+                    provider,
+                    pattern: pattern.to_lowercase(),
+                }
+            })
+            .collect();
+
+        let login_redirect = env_or("LOGIN_REDIRECT", "/protected".to_string());
+        if !is_local_path(&login_redirect) {
+            panic!(
+                "Invalid LOGIN_REDIRECT {}, expected a local path",
+                login_redirect
+            );
+        }
+
+        // A local path or an absolute URL, which is stored percent-encoded
+        let logout_redirect = env_or("LOGOUT_REDIRECT", "/".to_string());
+        let logout_redirect = if is_local_path(&logout_redirect) {
+            logout_redirect
+        } else {
+            match Url::parse(&logout_redirect) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => url.to_string(),
+                _ => panic!(
+                    "Invalid LOGOUT_REDIRECT {}, expected a local path or an http(s) URL",
+                    logout_redirect
+                ),
+            }
+        };
+
+        // host:port or unix:/path entries
+        let listen = match env_list("LISTEN") {
+            entries if entries.is_empty() => vec!["0.0.0.0:8000".to_string()],
+            entries => entries,
+        }
+        .into_iter()
+        .map(|entry| match entry.strip_prefix("unix:") {
+            Some(path) => ListenAddr::Unix(PathBuf::from(path)),
+            None => ListenAddr::Tcp(entry.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Invalid LISTEN entry {}, expected host:port or unix:/path",
+                    entry
+                )
+            })),
+        })
+        .collect();
+
+        let grpc_listen = env::var("GRPC_LISTEN").ok().map(|addr| {
+            addr.parse::<SocketAddr>()
+                .unwrap_or_else(|_| panic!("Invalid GRPC_LISTEN {}, expected host:port", addr))
+        });
+        let grpc_auth_token = env::var("GRPC_AUTH_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        if grpc_listen.is_some() && grpc_auth_token.is_none() {
+            panic!("GRPC_AUTH_TOKEN must be set when GRPC_LISTEN is");
+        }
+
+        // type=url entries, e.g. *=https://hooks.slack.com/services/...
+        let alert_webhooks = env_list("ALERT_WEBHOOKS")
+            .into_iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((kind, url)) if url.starts_with("http://") || url.starts_with("https://") => {
+                    (kind.to_string(), url.to_string())
+                }
+                _ => panic!(
+                    "Invalid ALERT_WEBHOOKS entry {}, expected type=https://...",
+                    entry
+                ),
+            })
+            .collect();
+
+        // syslog://host:port, file:/path or an http(s):// collector URL
+        let audit_export = env::var("AUDIT_EXPORT").ok().map(|target| {
+            if let Some(addr) = target.strip_prefix("syslog://") {
+                AuditSink::Syslog(addr.to_string())
+            } else if let Some(path) = target.strip_prefix("file:") {
+                AuditSink::File(PathBuf::from(path))
+            } else if target.starts_with("http://") || target.starts_with("https://") {
+                AuditSink::Http(target)
+            } else {
+                panic!(
+                    "Invalid AUDIT_EXPORT {}, expected syslog://host:port, file:/path or a URL",
+                    target
+                )
+            }
+        });
+
+        let audit_export_format = match env_or("AUDIT_EXPORT_FORMAT", "json".to_string()).as_str() {
+            "json" => AuditFormat::Json,
+            "cef" => AuditFormat::Cef,
+            other => panic!(
+                "Invalid AUDIT_EXPORT_FORMAT {}, expected json or cef",
+                other
+            ),
+        };
+
+        Self {
+            base_url,
+            callback_origins,
+            listen,
+            grpc_listen,
+            grpc_auth_token,
+            session_cookie_name,
+            cookie_domain,
+            cookie_secure,
+            cookie_same_site,
+            session_mode,
+            cors_allowed_origins: env_list("CORS_ALLOWED_ORIGINS"),
+            cors_allowed_methods: match env_list("CORS_ALLOWED_METHODS") {
+                methods if methods.is_empty() => vec!["GET".to_string(), "POST".to_string()],
+                methods => methods,
+            },
+            cors_allow_credentials: env_or("CORS_ALLOW_CREDENTIALS", false),
+            maintenance_mode: env_or("MAINTENANCE_MODE", false),
+            maintenance_retry_after_secs: env_or("MAINTENANCE_RETRY_AFTER_SECS", 300),
+            database_max_connections: env_or("DATABASE_MAX_CONNECTIONS", 5),
+            database_acquire_timeout_ms: env_or("DATABASE_ACQUIRE_TIMEOUT_MS", 3000),
+            database_statement_timeout_ms: env_or("DATABASE_STATEMENT_TIMEOUT_MS", 5000),
+            slow_query_threshold_ms: env_or("SLOW_QUERY_THRESHOLD_MS", 200),
+            log_span_timings: env_or("LOG_SPAN_TIMINGS", false),
+            access_log_format,
+            access_log_exclude: match env_list("ACCESS_LOG_EXCLUDE") {
+                paths if paths.is_empty() => vec![
+                    "/health".to_string(),
+                    "/ready".to_string(),
+                    "/static".to_string(),
+                ],
+                paths => paths,
+            },
+            request_body_limit: env_or("REQUEST_BODY_LIMIT", 1024 * 1024),
+            migration_lock_timeout_secs: env_or("MIGRATION_LOCK_TIMEOUT_SECS", 300),
+            session_prune_interval_secs: env_positive("SESSION_PRUNE_INTERVAL_SECS", 300),
+            session_prune_batch_size: env_positive("SESSION_PRUNE_BATCH_SIZE", 1000),
+            session_renewal_window_secs: env_or("SESSION_RENEWAL_WINDOW_SECS", 600),
+            session_renewal_secs: env_or("SESSION_RENEWAL_SECS", 3600),
+            session_max_lifetime_secs: env_or("SESSION_MAX_LIFETIME_SECS", 7 * 24 * 3600),
+            registration_mode,
+            registration_allowlist: env_list("REGISTRATION_ALLOWLIST")
+                .into_iter()
+                .map(|entry| entry.to_lowercase())
+                .collect(),
+            login_denylist,
+            login_policy_webhook: env::var("LOGIN_POLICY_WEBHOOK").ok(),
+            login_policy_fail_open: env_or("LOGIN_POLICY_FAIL_OPEN", false),
+            login_redirect,
+            role_login_redirects,
+            logout_redirect,
+            logout_redirect_allowlist: env_list("LOGOUT_REDIRECT_ALLOWLIST")
+                .into_iter()
+                .map(|entry| entry.trim_end_matches('/').to_string())
+                .collect(),
+            admin_emails: env_list("ADMIN_EMAILS")
+                .into_iter()
+                .map(|email| email.to_lowercase())
+                .collect(),
+            claim_role_mappings,
+            display_name_claims: env_list("DISPLAY_NAME_CLAIMS")
+                .iter()
+                .map(|entry| ClaimPath::parse(entry))
+                .collect(),
+            guest_session_days: env_or("GUEST_SESSION_DAYS", 30),
+            user_retention_days: env_or("USER_RETENTION_DAYS", 30),
+            user_purge_interval_secs: env_positive("USER_PURGE_INTERVAL_SECS", 3600),
+            audit_retention_months: env_or("AUDIT_RETENTION_MONTHS", 0),
+            token_refresh_interval_secs: env_positive("TOKEN_REFRESH_INTERVAL_SECS", 300),
+            token_refresh_window_secs: env_or("TOKEN_REFRESH_WINDOW_SECS", 600),
+            login_throttle_free_attempts: env_or("LOGIN_THROTTLE_FREE_ATTEMPTS", 5),
+            login_throttle_base_delay_secs: env_or("LOGIN_THROTTLE_BASE_DELAY_SECS", 1),
+            login_throttle_max_delay_secs: env_or("LOGIN_THROTTLE_MAX_DELAY_SECS", 900),
+            legacy_api_sunset: env::var("LEGACY_API_SUNSET").ok().map(|value| {
+                DateTime::parse_from_rfc3339(&value)
+                    .unwrap_or_else(|_| {
+                        panic!("Invalid LEGACY_API_SUNSET {}, expected RFC 3339", value)
+                    })
+                    .with_timezone(&Utc)
+            }),
+            csp_report_limit_per_minute: env_or("CSP_REPORT_LIMIT_PER_MINUTE", 30),
+            trusted_proxies: env_networks("TRUSTED_PROXIES"),
+            admin_allowed_networks: env_networks("ADMIN_ALLOWED_CIDRS"),
+            admin_require_client_cert: env_or("ADMIN_REQUIRE_CLIENT_CERT", false),
+            client_cert_verify_header: env_or(
+                "CLIENT_CERT_VERIFY_HEADER",
+                "x-ssl-client-verify".to_string(),
+            ),
+            client_cert_fingerprint_header: env_or(
+                "CLIENT_CERT_FINGERPRINT_HEADER",
+                "x-ssl-client-fingerprint".to_string(),
+            ),
+            admin_client_cert_fingerprints: env_list("ADMIN_CLIENT_CERT_FINGERPRINTS")
+                .into_iter()
+                .map(|fingerprint| normalize_fingerprint(&fingerprint))
+                .collect(),
+            audit_export,
+            audit_export_format,
+            audit_export_interval_secs: env_positive("AUDIT_EXPORT_INTERVAL_SECS", 10),
+            alert_webhooks,
+            sentry_dsn: env::var("SENTRY_DSN").ok(),
+            sentry_environment: env::var("SENTRY_ENVIRONMENT").ok(),
+            geoip_database: env::var("GEOIP_DATABASE").ok(),
+            max_travel_speed_kmh: env_or("MAX_TRAVEL_SPEED_KMH", 1000.0),
+            session_bind_ip_range: env_or("SESSION_BIND_IP_RANGE", false),
+            session_bind_user_agent: env_or("SESSION_BIND_USER_AGENT", false),
+            google_allowed_domains: env_list("GOOGLE_ALLOWED_DOMAINS")
+                .into_iter()
+                .map(|domain| domain.to_lowercase())
+                .collect(),
+            google: ProviderSettings::from_env("GOOGLE", &["openid", "profile", "email"]),
+            twitter: ProviderSettings::from_env("TWITTER", &["tweet.read", "users.read"]),
+            google_one_tap: env_or("GOOGLE_ONE_TAP", false),
+            disposable_email_domains: include_str!("disposable_domains.txt")
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .chain(env_list("DISPOSABLE_EMAIL_DOMAINS"))
+                .map(|domain| domain.to_lowercase())
+                .collect(),
+        }
+    }
+
+    // The callback origin serving this Host header, or the default one for unknown
+    // hosts so a forged header can't send authorization codes elsewhere
+    pub fn callback_origin(&self, host: Option<&str>) -> &str {
+        host.and_then(|host| {
+            self.callback_origins.iter().find(|origin| {
+                origin
+                    .split_once("://")
+                    .is_some_and(|(_, origin_host)| origin_host.eq_ignore_ascii_case(host))
+            })
+        })
+        .unwrap_or(&self.callback_origins[0])
+    }
+
+    // Origin of BASE_URL, used as the postMessage target for silent re-authentication
+    pub fn public_origin(&self) -> String {
+        Url::parse(&self.base_url)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_else(|_| self.base_url.clone())
+    }
+}
+
+// Read and parse an optional environment variable, falling back to a default
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+// For values where zero or a typo would break a background loop, so a bad
+// value stops startup instead of silently falling back
+fn env_positive<T: FromStr + PartialOrd + From<u8>>(name: &str, default: T) -> T {
+    let Ok(value) = env::var(name) else {
+        return default;
+    };
+
+    match value.parse::<T>() {
+        Ok(parsed) if parsed >= T::from(1) => parsed,
+        _ => panic!(
+            "{} must be a whole number of at least 1, got {:?}",
+            name, value
+        ),
+    }
+}
+
+// The app's own host must be the cookie domain or one of its subdomains,
+// otherwise browsers silently drop the cookie
+fn validate_cookie_domain(base_url: &str, domain: &str) {
+    let url = Url::parse(base_url).expect("BASE_URL must be a valid URL");
+    let host = url.host_str().expect("BASE_URL must include a host");
+
+    if host != domain && !host.ends_with(&format!(".{}", domain)) {
+        panic!(
+            "COOKIE_DOMAIN {} does not match the BASE_URL host {}",
+            domain, host
+        );
+    }
+}
+
+// Read a comma-separated environment variable, ignoring empty entries
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// A path on this site. Only visible ASCII other than \ is accepted: browsers read
+// // and /\ as another host and drop tabs and newlines (/%09/evil.com), and
+// anything else can't go into a Location header.
+pub fn is_local_path(path: &str) -> bool {
+    path.starts_with('/')
+        && !path.starts_with("//")
+        && path
+            .bytes()
+            .all(|byte| byte.is_ascii_graphic() && byte != b'\\')
+}
+
+// Fingerprints are compared as lowercase hex without colon separators
+pub fn normalize_fingerprint(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| *c != ':')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+// Parse a list of CIDR ranges; bare addresses are treated as single-host networks
+fn env_networks(name: &str) -> Vec<IpNet> {
+    env_list(name)
+        .iter()
+        .map(|value| {
+            value
+                .parse::<IpNet>()
+                .ok()
+                .or_else(|| {
+                    let ip = value.parse::<IpAddr>().ok()?;
+                    IpNet::new(ip, if ip.is_ipv4() { 32 } else { 128 }).ok()
+                })
+                .unwrap_or_else(|| panic!("Invalid network {} in {}", value, name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_paths() {
+        for path in ["/", "/protected", "/a/b?c=d#e", "/%2F%2Fevil.com"] {
+            assert!(is_local_path(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn paths_that_leave_the_site() {
+        for path in [
+            "",
+            "protected",
+            "https://evil.com",
+            "//evil.com",
+            "/\\evil.com",
+            "/\t/evil.com",
+            "/ok\r\nSet-Cookie: a=b",
+            "/caf\u{e9}",
+        ] {
+            assert!(!is_local_path(path), "{:?}", path);
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    http::{header, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
 };
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::handlers::html::escape_html;
+use crate::services::pool_metrics::record_acquire_timeout;
+
 #[derive(Debug, Error)]
 pub enum ApiError {
     #[error("Database error: {0}")]
@@ -24,26 +28,73 @@ pub enum ApiError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Forbidden")]
+    Forbidden,
+
     #[error("Bad request: {0}")]
     BadRequest(String),
+
+    #[error("Login rejected: {0}")]
+    LoginRejected(String),
+
+    #[error("Access requested for {0}")]
+    AccessRequested(String),
+
+    #[error("No account for {0}")]
+    AccountNotFound(String),
+
+    #[error("Too many requests, retry after {0:?}")]
+    TooManyRequests(Duration),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
+            // Saturated pool or cancelled statement: fail fast and let the client retry
+            Self::Database(e) if is_overload(&e) => {
+                tracing::warn!("Database overloaded: {}", e);
+                if matches!(e, sqlx::Error::PoolTimedOut) {
+                    record_acquire_timeout();
+                }
+                let mut response = server_error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Service temporarily overloaded, please retry",
+                    format!("Database overloaded: {}", e),
+                );
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+                return response;
+            }
+            // The primary is failing over: writes are refused until a new one is promoted
+            Self::Database(e) if is_read_only(&e) => {
+                tracing::warn!("Database is read-only: {}", e);
+                let mut response = (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, "30")],
+                    Html(read_only_page()),
+                )
+                    .into_response();
+                response
+                    .extensions_mut()
+                    .insert(ServerError(format!("Database is read-only: {}", e)));
+                return response;
+            }
             Self::Database(e) => {
                 tracing::error!("Database error: {}", e);
-                (
+                return server_error(
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    "Database error occurred".to_string(),
-                )
+                    "Database error occurred",
+                    format!("Database error: {}", e),
+                );
             }
             Self::Request(e) => {
                 tracing::error!("HTTP request error: {}", e);
-                (
+                return server_error(
                     StatusCode::BAD_GATEWAY,
-                    "External service error".to_string(),
-                )
+                    "External service error",
+                    format!("HTTP request error: {}", e),
+                );
             }
             Self::TokenError(e) => {
                 tracing::error!("OAuth token error: {}", e);
@@ -56,9 +107,142 @@ impl IntoResponse for ApiError {
                 StatusCode::UNAUTHORIZED,
                 "You are not authorized to access this resource".to_string(),
             ),
+            Self::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "You do not have permission to access this resource".to_string(),
+            ),
             Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            Self::LoginRejected(reason) => {
+                return (StatusCode::FORBIDDEN, Html(login_rejected_page(&reason))).into_response();
+            }
+            Self::AccessRequested(email) => {
+                return (StatusCode::FORBIDDEN, Html(access_requested_page(&email)))
+                    .into_response();
+            }
+            Self::AccountNotFound(email) => {
+                return (StatusCode::FORBIDDEN, Html(account_not_found_page(&email)))
+                    .into_response();
+            }
+            Self::TooManyRequests(retry_after) => {
+                let retry_after = retry_after.as_secs().max(1).to_string();
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after)],
+                    "Too many failed login attempts, please try again later",
+                )
+                    .into_response();
+            }
         };
 
         (status, error_message).into_response()
     }
 }
+
+// No free pooled connection in time, or the server cancelled a statement that ran
+// past statement_timeout (SQLSTATE 57014)
+fn is_overload(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(e) => e.code().as_deref() == Some("57014"),
+        _ => false,
+    }
+}
+
+// Write refused by a standby (25006), or the server going away underneath us
+// while it is shut down or not yet accepting connections (57P01-57P03)
+fn is_read_only(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Database(e) => matches!(
+            e.code().as_deref(),
+            Some("25006" | "57P01" | "57P02" | "57P03")
+        ),
+        _ => false,
+    }
+}
+
+// Failures on our side, attached to the response for the error reporting middleware
+#[derive(Clone)]
+pub struct ServerError(pub String);
+
+fn server_error(status: StatusCode, message: &str, detail: String) -> Response {
+    let mut response = (status, message.to_string()).into_response();
+    response.extensions_mut().insert(ServerError(detail));
+    response
+}
+
+fn login_rejected_page(reason: &str) -> String {
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Sign-in not allowed</title>
+        </head>
+        <body>
+            <h1>Sign-in not allowed</h1>
+            <p>{}</p>
+            <p><a href="/login">Try a different account</a></p>
+        </body>
+        </html>
+        "#,
+        escape_html(reason)
+    )
+}
+
+fn account_not_found_page(email: &str) -> String {
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Account not found</title>
+        </head>
+        <body>
+            <h1>Account not found</h1>
+            <p>There is no account for <strong>{}</strong>. Accounts are set up by an
+               administrator; please contact yours to get access.</p>
+            <p><a href="/login">Try a different account</a></p>
+        </body>
+        </html>
+        "#,
+        escape_html(email)
+    )
+}
+
+fn read_only_page() -> String {
+    r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Temporarily read-only</title>
+        </head>
+        <body>
+            <h1>Temporarily read-only</h1>
+            <p>We can't save changes right now. Everything else keeps working, so please
+               try this again in a minute.</p>
+            <p><a href="/">Back to the home page</a></p>
+        </body>
+        </html>
+        "#
+    .to_string()
+}
+
+fn access_requested_page(email: &str) -> String {
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Access requested</title>
+        </head>
+        <body>
+            <h1>Access requested</h1>
+            <p>Accounts are currently by invitation only. We've recorded a request for
+               <strong>{}</strong>, and an administrator will review it.</p>
+            <p><a href="/">Back to the home page</a></p>
+        </body>
+        </html>
+        "#,
+        escape_html(email)
+    )
+}
@@ -0,0 +1,190 @@
+use axum_extra::extract::cookie::{Cookie, PrivateCookieJar};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+
+use crate::config::settings::SessionMode;
+use crate::handlers::UserProfile;
+use crate::services::auth_events::{self, AuthEvent};
+use crate::services::stateless_session::{self, SessionClaims};
+use crate::state::AppState;
+
+pub mod proto {
+    tonic::include_proto!("oauth_axum.session.v1");
+}
+
+use proto::session_service_server::{SessionService, SessionServiceServer};
+use proto::{Identity, RevokeSessionResponse, SessionRequest, User, ValidateSessionResponse};
+
+// Session checks for sibling backends, so they don't need the cookie key or the
+// session tables themselves
+pub struct SessionGrpc {
+    state: AppState,
+}
+
+impl SessionGrpc {
+    // Decrypted value of a session cookie as the browser sent it
+    fn cookie_value(&self, session_cookie: String) -> Option<String> {
+        let cookie = Cookie::new(
+            self.state.settings.session_cookie_name.clone(),
+            session_cookie,
+        );
+        PrivateCookieJar::new(self.state.key.clone())
+            .decrypt(cookie)
+            .map(|cookie| cookie.value().to_string())
+    }
+
+    // Claims of a live stateless session
+    fn live_claims(&self, value: &str) -> Option<SessionClaims> {
+        SessionClaims::decode(value)
+            .filter(|claims| !self.state.revoked_sessions.is_revoked(&claims.sid))
+    }
+
+    // User of a live session. Fingerprint binding can't be checked here, since the
+    // caller isn't the client the session belongs to.
+    async fn session_user(&self, session_cookie: String) -> Result<Option<UserProfile>, Status> {
+        let Some(value) = self.cookie_value(session_cookie) else {
+            return Ok(None);
+        };
+
+        let user = match self.state.settings.session_mode {
+            SessionMode::Database => UserProfile::load(&self.state.db, &value, None).await,
+            SessionMode::Stateless => match self.live_claims(&value) {
+                Some(claims) => Ok(Some(UserProfile::from_claims(claims))),
+                None => Ok(None),
+            },
+        };
+
+        user.map_err(internal)
+    }
+}
+
+#[tonic::async_trait]
+impl SessionService for SessionGrpc {
+    async fn validate_session(
+        &self,
+        request: Request<SessionRequest>,
+    ) -> Result<Response<ValidateSessionResponse>, Status> {
+        let session_cookie = request.into_inner().session_cookie;
+
+        // Stateless sessions are answered from the cookie alone
+        let session = match self.state.settings.session_mode {
+            SessionMode::Database => self
+                .session_user(session_cookie)
+                .await?
+                .map(|user| (user.id, user.roles)),
+            SessionMode::Stateless => self
+                .cookie_value(session_cookie)
+                .and_then(|value| self.live_claims(&value))
+                .map(|claims| (claims.uid, claims.roles)),
+        };
+
+        Ok(Response::new(match session {
+            Some((user_id, roles)) => ValidateSessionResponse {
+                valid: true,
+                user_id,
+                roles,
+            },
+            None => ValidateSessionResponse::default(),
+        }))
+    }
+
+    async fn get_user(&self, request: Request<SessionRequest>) -> Result<Response<User>, Status> {
+        let user = match self
+            .session_user(request.into_inner().session_cookie)
+            .await?
+        {
+            Some(user) => user.with_details(&self.state.db).await.map_err(internal)?,
+            None => None,
+        };
+        let user = user.ok_or_else(|| Status::unauthenticated("Invalid or expired session"))?;
+
+        Ok(Response::new(User {
+            id: user.id,
+            email: user.email,
+            display_name: user.display_name,
+            roles: user.roles,
+            identities: user
+                .identities
+                .into_iter()
+                .map(|identity| Identity {
+                    provider: identity.provider,
+                    provider_user_id: identity.provider_user_id,
+                })
+                .collect(),
+        }))
+    }
+
+    // Deleting the row also revokes stateless sessions on every instance, through
+    // the sessions delete notification
+    async fn revoke_session(
+        &self,
+        request: Request<SessionRequest>,
+    ) -> Result<Response<RevokeSessionResponse>, Status> {
+        let session_id = self
+            .cookie_value(request.into_inner().session_cookie)
+            .and_then(|value| stateless_session::session_id(&self.state.settings, &value));
+        let Some(session_id) = session_id else {
+            return Ok(Response::new(RevokeSessionResponse { revoked: false }));
+        };
+
+        let revoked: Option<(i32,)> =
+            sqlx::query_as("DELETE FROM sessions WHERE session_id = $1 RETURNING user_id")
+                .bind(&session_id)
+                .fetch_optional(&self.state.db)
+                .await
+                .map_err(internal)?;
+
+        if let Some((user_id,)) = revoked {
+            let event = AuthEvent::SessionRevoked { reason: "service" };
+            auth_events::record(&self.state.db, user_id, event)
+                .await
+                .map_err(internal)?;
+        }
+
+        Ok(Response::new(RevokeSessionResponse {
+            revoked: revoked.is_some(),
+        }))
+    }
+}
+
+fn internal(e: sqlx::Error) -> Status {
+    tracing::error!("gRPC session service database error: {}", e);
+    Status::internal("Database error")
+}
+
+// Serve the session service until the listener fails. Every call must carry
+// GRPC_AUTH_TOKEN as a bearer token.
+pub async fn serve(state: AppState, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+    // Compared as digests so the comparison doesn't depend on where the tokens differ
+    let expected = Sha256::digest(
+        state
+            .settings
+            .grpc_auth_token
+            .as_deref()
+            .expect("GRPC_AUTH_TOKEN is checked at startup")
+            .as_bytes(),
+    );
+    let authorize = move |request: Request<()>| {
+        let authorized = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| Sha256::digest(token.as_bytes()) == expected);
+
+        if !authorized {
+            return Err(Status::unauthenticated("Missing or invalid service token"));
+        }
+        Ok(request)
+    };
+
+    tonic::transport::Server::builder()
+        .add_service(SessionServiceServer::with_interceptor(
+            SessionGrpc { state },
+            authorize,
+        ))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
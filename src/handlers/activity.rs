@@ -0,0 +1,202 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::UserProfile;
+use crate::middleware::CspNonce;
+use crate::oauth::Provider;
+use crate::services::auth_events::{list_events, AuthEventRecord, EventFilter};
+use crate::state::AppState;
+
+// Events shown per page; older ones are reached through the "Older" link
+const PAGE_SIZE: i64 = 25;
+// Most recently seen devices listed above the events
+const DEVICE_LIMIT: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    // Show events older than this event id
+    pub before: Option<i64>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Device {
+    ip_address: String,
+    user_agent: String,
+    first_seen_at: Option<DateTime<Utc>>,
+    last_seen_at: Option<DateTime<Utc>>,
+}
+
+fn provider_label(provider: Option<&str>) -> &str {
+    match provider {
+        Some(provider) => Provider::parse(provider).map_or(provider, |provider| provider.label()),
+        None => "an unknown provider",
+    }
+}
+
+// Plain-language description of an auth event for its owner
+fn describe(event: &AuthEventRecord) -> String {
+    let provider = provider_label(event.provider.as_deref());
+    let detail = event.detail.as_deref().unwrap_or("");
+
+    match event.kind.as_str() {
+        "user_created" => format!("Account created with {}", provider),
+        "user_imported" => "Account imported by an administrator".to_string(),
+        "identity_linked" => format!("{} account linked", provider),
+        "tokens_revoked" => format!("Access to your {} account revoked", provider),
+        "session_issued" => format!("Signed in with {}", provider),
+        "session_revoked" => match detail {
+            "logout" => "Signed out".to_string(),
+            "logout_everywhere" => "Signed out on all devices".to_string(),
+            reason => format!("Session ended ({})", reason),
+        },
+        "role_granted" => format!("Role {} granted", detail),
+        "role_revoked" => format!("Role {} removed", detail),
+        "user_deleted" => "Account deleted".to_string(),
+        "user_restored" => "Account restored".to_string(),
+        kind => kind.to_string(),
+    }
+}
+
+fn format_time(value: Option<DateTime<Utc>>) -> String {
+    value.map_or(String::new(), |at| {
+        at.format("%Y-%m-%d %H:%M UTC").to_string()
+    })
+}
+
+// The signed-in user's own sign-ins, devices and security events, newest first
+pub async fn activity_page(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Html<String>, ApiError> {
+    let filter = EventFilter {
+        user_id: Some(user.id),
+        kind: None,
+        before: query.before,
+        limit: Some(PAGE_SIZE),
+    };
+    let events = list_events(&state.db, &filter).await?;
+
+    let devices: Vec<Device> = sqlx::query_as(
+        "SELECT ip_address, user_agent, first_seen_at, last_seen_at FROM user_devices
+         WHERE user_id = $1
+         ORDER BY last_seen_at DESC
+         LIMIT $2",
+    )
+    .bind(user.id)
+    .bind(DEVICE_LIMIT)
+    .fetch_all(&state.db)
+    .await?;
+
+    let device_rows = devices
+        .iter()
+        .map(|device| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&device.ip_address),
+                escape_html(&device.user_agent),
+                format_time(device.first_seen_at),
+                format_time(device.last_seen_at)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let event_rows = events
+        .iter()
+        .map(|event| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                format_time(Some(event.occurred_at)),
+                escape_html(&describe(event)),
+                escape_html(event.ip_address.as_deref().unwrap_or(""))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // A full page means there may be older events
+    let older = match events.last() {
+        Some(last) if events.len() as i64 == PAGE_SIZE => format!(
+            r#"<a href="/protected/activity?before={}" class="button">Older</a>"#,
+            last.id
+        ),
+        _ => String::new(),
+    };
+    let newest = if query.before.is_some() {
+        r#"<a href="/protected/activity" class="button">Newest</a>"#
+    } else {
+        ""
+    };
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Account Activity</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 800px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                table {{
+                    width: 100%;
+                    margin: 20px 0;
+                    text-align: left;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    text-decoration: none;
+                    border-radius: 5px;
+                    margin: 10px 10px 10px 0;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Account Activity</h1>
+                <p>If something here wasn't you, sign out everywhere and contact support.</p>
+                <h2>Devices</h2>
+                <table>
+                    <tr><th>IP address</th><th>Browser</th><th>First seen</th><th>Last seen</th></tr>
+                    {}
+                </table>
+                <h2>Events</h2>
+                <table>
+                    <tr><th>When</th><th>What</th><th>IP address</th></tr>
+                    {}
+                </table>
+                {}
+                {}
+                <a href="/protected" class="button">Back</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        device_rows,
+        event_rows,
+        newest,
+        older,
+        nonce = nonce
+    )))
+}
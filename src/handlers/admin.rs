@@ -0,0 +1,933 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension, Form, Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+use crate::config::reload::{reload, ReloadedConfig};
+use crate::errors::ApiError;
+use crate::handlers::conditional::conditional_json;
+use crate::handlers::html::escape_html;
+use crate::handlers::UserProfile;
+use crate::middleware::CspNonce;
+use crate::oauth::Provider;
+use crate::services::analytics::{auth_stats, AuthStats};
+use crate::services::auth_events::{self, list_events, AuthEvent, AuthEventRecord, EventFilter};
+use crate::services::csp_reports::{list_reports, CspReport};
+use crate::services::invalidation::broadcast_maintenance;
+use crate::services::legal::{publish_document, LegalDocument};
+use crate::services::providers::{enabled_providers, set_enabled};
+use crate::services::registration::{access_requests, invite, revoke_invitation, AccessRequest};
+use crate::services::session::force_logout;
+use crate::services::user_import::{
+    find_import, import_errors, parse_csv, start_import, ImportError, ImportRow, UserImport,
+};
+use crate::state::AppState;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdminUser {
+    pub id: i32,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub roles: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub last_login_provider: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    #[default]
+    Id,
+    Email,
+    CreatedAt,
+    LastLoginAt,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearch {
+    // Substring of the email or display name
+    pub q: Option<String>,
+    pub provider: Option<String>,
+    pub role: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    #[serde(default)]
+    pub sort: UserSort,
+    #[serde(default)]
+    pub desc: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserPage {
+    pub users: Vec<AdminUser>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+// Shared by the page and count queries; $1 pattern, $2 provider, $3 role
+const USER_FILTER: &str = "deleted_at IS NULL
+    AND ($1::text IS NULL OR email ILIKE $1 OR display_name ILIKE $1)
+    AND ($2::text IS NULL OR EXISTS (
+        SELECT 1 FROM user_identities
+        WHERE user_identities.user_id = users.id AND user_identities.provider = $2
+    ))
+    AND ($3::text IS NULL OR roles @> ARRAY[$3])";
+
+// Escape LIKE wildcards so the search term is matched literally
+fn like_pattern(q: &str) -> String {
+    let escaped = q
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(search): Query<UserSearch>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let page = search.page.unwrap_or(1).max(1);
+    let per_page = search
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let pattern = search
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(like_pattern);
+    let provider = search.provider.filter(|provider| !provider.is_empty());
+    let role = search.role.filter(|role| !role.is_empty());
+
+    // Column names come from the enum, never from the query string
+    let column = match search.sort {
+        UserSort::Id => "id",
+        UserSort::Email => "email",
+        UserSort::CreatedAt => "created_at",
+        UserSort::LastLoginAt => "last_login_at",
+    };
+    let direction = if search.desc {
+        "DESC NULLS LAST"
+    } else {
+        "ASC"
+    };
+
+    let users = sqlx::query_as::<_, AdminUser>(&format!(
+        "SELECT id, email, display_name, roles, created_at, last_login_at, last_login_provider
+         FROM users
+         WHERE {}
+         ORDER BY {} {}, id
+         LIMIT $4 OFFSET $5",
+        USER_FILTER, column, direction
+    ))
+    .bind(&pattern)
+    .bind(&provider)
+    .bind(&role)
+    .bind(per_page)
+    .bind((page - 1) * per_page)
+    .fetch_all(&state.db)
+    .await?;
+
+    let (total,): (i64,) =
+        sqlx::query_as(&format!("SELECT COUNT(*) FROM users WHERE {}", USER_FILTER))
+            .bind(&pattern)
+            .bind(&provider)
+            .bind(&role)
+            .fetch_one(&state.db)
+            .await?;
+
+    let result = UserPage {
+        users,
+        total,
+        page,
+        per_page,
+    };
+
+    Ok(conditional_json(&headers, &result))
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportUser {
+    pub id: i32,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub roles: Vec<String>,
+    // provider:provider_user_id pairs
+    pub identities: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub last_login_provider: Option<String>,
+}
+
+// Users fetched per database round trip while streaming an export
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+async fn export_batch(db: &PgPool, after_id: i32) -> Result<Vec<ExportUser>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT users.id, users.email, users.display_name, users.roles,
+                COALESCE(
+                    array_agg(user_identities.provider || ':' || user_identities.provider_user_id)
+                        FILTER (WHERE user_identities.id IS NOT NULL),
+                    '{}'
+                ) AS identities,
+                users.created_at, users.last_login_at, users.last_login_provider
+         FROM users
+         LEFT JOIN user_identities ON user_identities.user_id = users.id
+         WHERE users.id > $1 AND users.deleted_at IS NULL
+         GROUP BY users.id
+         ORDER BY users.id
+         LIMIT $2",
+    )
+    .bind(after_id)
+    .bind(EXPORT_BATCH_SIZE)
+    .fetch_all(db)
+    .await
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_timestamp(value: Option<DateTime<Utc>>) -> String {
+    value.map(|at| at.to_rfc3339()).unwrap_or_default()
+}
+
+impl ExportFormat {
+    fn header(self) -> &'static str {
+        match self {
+            Self::Csv => concat!(
+                "id,email,display_name,roles,identities,",
+                "created_at,last_login_at,last_login_provider\n"
+            ),
+            Self::Jsonl => "",
+        }
+    }
+
+    fn render(self, user: &ExportUser) -> String {
+        match self {
+            Self::Csv => format!(
+                "{},{},{},{},{},{},{},{}\n",
+                user.id,
+                csv_field(&user.email),
+                csv_field(user.display_name.as_deref().unwrap_or("")),
+                csv_field(&user.roles.join(";")),
+                csv_field(&user.identities.join(";")),
+                csv_timestamp(user.created_at),
+                csv_timestamp(user.last_login_at),
+                csv_field(user.last_login_provider.as_deref().unwrap_or(""))
+            ),
+            Self::Jsonl => serde_json::to_string(user).unwrap_or_default() + "\n",
+        }
+    }
+}
+
+// Stream all users in keyset-paginated batches so memory use stays flat
+pub async fn export_users(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let format = query.format;
+
+    let rows = stream::unfold(Some((state.db, 0)), move |cursor| async move {
+        let (db, after_id) = cursor?;
+
+        let users = match export_batch(&db, after_id).await {
+            Ok(users) => users,
+            Err(e) => {
+                tracing::error!("User export failed: {}", e);
+                return Some((Err(e), None));
+            }
+        };
+
+        let last_id = users.last()?.id;
+        let chunk: String = users.iter().map(|user| format.render(user)).collect();
+
+        Some((Ok(chunk), Some((db, last_id))))
+    });
+
+    let body = stream::once(async move { Ok(format.header().to_string()) }).chain(rows);
+
+    let (content_type, filename) = match format {
+        ExportFormat::Csv => ("text/csv", "users.csv"),
+        ExportFormat::Jsonl => ("application/x-ndjson", "users.jsonl"),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdminIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub scopes: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+async fn user_identities(db: &PgPool, user_id: i32) -> Result<Vec<AdminIdentity>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, user_id, provider, provider_user_id, scopes, created_at, last_login_at
+         FROM user_identities WHERE user_id = $1 ORDER BY id",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn list_user_identities(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let identities = user_identities(&state.db, id).await?;
+
+    Ok(conditional_json(&headers, &identities))
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentityOwner {
+    pub user: AdminUser,
+    pub identities: Vec<AdminIdentity>,
+}
+
+// Find the account behind a provider identity, e.g. a Google sub or Twitter id from
+// an abuse report. Twitter handles work too when prefixed with @, since Twitter
+// accounts are keyed by handle. Soft-deleted users are included.
+pub async fn find_identity_owner(
+    State(state): State<AppState>,
+    Path((provider, provider_user_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let Some(provider) = Provider::parse(&provider) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let user: Option<AdminUser> = match (provider, provider_user_id.strip_prefix('@')) {
+        (Provider::Twitter, Some(handle)) => {
+            sqlx::query_as(
+                "SELECT id, email, display_name, roles, created_at, last_login_at,
+                        last_login_provider
+                 FROM users WHERE lower(email) = lower($1)",
+            )
+            .bind(format!("{}@twitter.local", handle))
+            .fetch_optional(&state.db)
+            .await?
+        }
+        _ => {
+            sqlx::query_as(
+                "SELECT users.id, users.email, users.display_name, users.roles,
+                        users.created_at, users.last_login_at, users.last_login_provider
+                 FROM user_identities
+                 JOIN users ON users.id = user_identities.user_id
+                 WHERE user_identities.provider = $1
+                   AND user_identities.provider_user_id = $2",
+            )
+            .bind(provider.as_str())
+            .bind(&provider_user_id)
+            .fetch_optional(&state.db)
+            .await?
+        }
+    };
+
+    let Some(user) = user else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let identities = user_identities(&state.db, user.id).await?;
+
+    Ok(conditional_json(
+        &headers,
+        &IdentityOwner { user, identities },
+    ))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportStarted {
+    pub id: i32,
+}
+
+// Queue a bulk import of users migrated from another system. The body is a JSON
+// array, or CSV when sent as text/csv; rows are processed in the background.
+pub async fn import_users(
+    State(state): State<AppState>,
+    admin: UserProfile,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<ImportStarted>), ApiError> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/csv"));
+
+    let rows: Vec<ImportRow> = if is_csv {
+        parse_csv(&body).map_err(ApiError::BadRequest)?
+    } else {
+        serde_json::from_str(&body)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid import JSON: {}", e)))?
+    };
+
+    if rows.is_empty() {
+        return Err(ApiError::BadRequest("No users to import".to_string()));
+    }
+
+    let id = start_import(&state.db, rows, admin.id).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(ImportStarted { id })))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportStatus {
+    #[serde(flatten)]
+    pub import: UserImport,
+    pub errors: Vec<ImportError>,
+}
+
+pub async fn get_import(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response, ApiError> {
+    let Some(import) = find_import(&state.db, id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let errors = import_errors(&state.db, id).await?;
+
+    Ok(Json(ImportStatus { import, errors }).into_response())
+}
+
+// Soft delete: the user is signed out and can't log in, and is purged after
+// USER_RETENTION_DAYS unless restored
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let deleted =
+        sqlx::query("UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .execute(&state.db)
+            .await?
+            .rows_affected();
+
+    if deleted == 0 {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    auth_events::record(&state.db, id, AuthEvent::UserDeleted).await?;
+
+    let revoked = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(id)
+        .execute(&state.db)
+        .await?
+        .rows_affected();
+
+    if revoked > 0 {
+        let event = AuthEvent::SessionRevoked {
+            reason: "user_deleted",
+        };
+        auth_events::record(&state.db, id, event).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn restore_user(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let restored =
+        sqlx::query("UPDATE users SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL")
+            .bind(id)
+            .execute(&state.db)
+            .await?
+            .rows_affected();
+
+    if restored == 0 {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    auth_events::record(&state.db, id, AuthEvent::UserRestored).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ForceLogoutRequest {
+    // Keep the user from signing in again for this long; 0 or absent doesn't block
+    #[serde(default)]
+    pub block_minutes: i64,
+}
+
+// Longest sign-in block a force-logout can set: 30 days
+const MAX_LOGIN_BLOCK_MINUTES: i64 = 30 * 24 * 60;
+
+fn block_duration(minutes: i64) -> Option<Duration> {
+    (minutes > 0).then(|| Duration::minutes(minutes.min(MAX_LOGIN_BLOCK_MINUTES)))
+}
+
+pub async fn force_logout_user(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    request: Option<Json<ForceLogoutRequest>>,
+) -> Result<Response, ApiError> {
+    let Json(request) = request.unwrap_or_default();
+
+    let Some(result) = force_logout(&state.db, id, block_duration(request.block_minutes)).await?
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    tracing::warn!("Admin forced logout of user {}: {:?}", id, result);
+
+    Ok(Json(result).into_response())
+}
+
+// Audit view over the auth event stream, newest first
+pub async fn list_auth_events(
+    State(state): State<AppState>,
+    Query(filter): Query<EventFilter>,
+) -> Result<Json<Vec<AuthEventRecord>>, ApiError> {
+    Ok(Json(list_events(&state.db, &filter).await?))
+}
+
+// Unknown users who tried to sign in while registration is restricted
+pub async fn list_access_requests(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AccessRequest>>, ApiError> {
+    Ok(Json(access_requests(&state.db).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvitationRequest {
+    pub email: String,
+}
+
+// Let an email create an account on its next sign-in; there is no mailer, so the
+// admin tells the person themselves
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    admin: UserProfile,
+    Json(request): Json<InvitationRequest>,
+) -> Result<StatusCode, ApiError> {
+    let email = request.email.trim();
+    if !email.contains('@') {
+        return Err(ApiError::BadRequest("Invalid email address".to_string()));
+    }
+
+    invite(&state.db, email, admin.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_invitation(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    Ok(if revoke_invitation(&state.db, &email).await? {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    })
+}
+
+// Let users from a bundled disposable domain sign in anyway
+pub async fn allow_email_domain(
+    State(state): State<AppState>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    sqlx::query("INSERT INTO email_domain_overrides (domain) VALUES ($1) ON CONFLICT DO NOTHING")
+        .bind(domain.to_lowercase())
+        .execute(&state.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn disallow_email_domain(
+    State(state): State<AppState>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    sqlx::query("DELETE FROM email_domain_overrides WHERE domain = $1")
+        .bind(domain.to_lowercase())
+        .execute(&state.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderStatus {
+    pub provider: &'static str,
+    pub enabled: bool,
+}
+
+pub async fn list_providers(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ProviderStatus>>, ApiError> {
+    let enabled = enabled_providers(&state.db).await?;
+
+    Ok(Json(
+        Provider::ALL
+            .into_iter()
+            .map(|provider| ProviderStatus {
+                provider: provider.as_str(),
+                enabled: enabled.contains(&provider),
+            })
+            .collect(),
+    ))
+}
+
+// Switch a provider back on, or off when it is compromised or misbehaving. Takes
+// effect on the next login without a redeploy; existing sessions are kept.
+pub async fn enable_provider(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    toggle_provider(&state, &provider, true).await
+}
+
+pub async fn disable_provider(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    toggle_provider(&state, &provider, false).await
+}
+
+async fn toggle_provider(
+    state: &AppState,
+    provider: &str,
+    enabled: bool,
+) -> Result<StatusCode, ApiError> {
+    let Some(provider) = Provider::parse(provider) else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    set_enabled(&state.db, provider, enabled).await?;
+    tracing::warn!(
+        "Provider {} {}",
+        provider.as_str(),
+        if enabled { "enabled" } else { "disabled" }
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_csp_reports(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CspReport>>, ApiError> {
+    Ok(Json(list_reports(&state.db).await?))
+}
+
+// Toggle maintenance mode on every instance; admins and health checks are unaffected
+pub async fn enable_maintenance(State(state): State<AppState>) -> Result<StatusCode, ApiError> {
+    state.maintenance.set(true);
+    broadcast_maintenance(&state.db, true).await?;
+    tracing::warn!("Maintenance mode enabled");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn disable_maintenance(State(state): State<AppState>) -> Result<StatusCode, ApiError> {
+    state.maintenance.set(false);
+    broadcast_maintenance(&state.db, false).await?;
+    tracing::info!("Maintenance mode disabled");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Same as sending SIGHUP: re-read .env and apply the reloadable settings
+pub async fn reload_config(
+    State(state): State<AppState>,
+) -> Result<Json<ReloadedConfig>, ApiError> {
+    let config = reload(&state).map_err(ApiError::BadRequest)?;
+    tracing::warn!("Configuration reloaded: {:?}", config);
+
+    Ok(Json(config))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogFilter {
+    pub filter: String,
+}
+
+pub async fn get_log_filter(State(state): State<AppState>) -> Json<LogFilter> {
+    Json(LogFilter {
+        filter: state.live_config.current_log_filter(),
+    })
+}
+
+// Takes RUST_LOG syntax, e.g. oauth_axum::handlers::auth=debug; lasts until the
+// next reload or restart
+pub async fn set_log_filter(
+    State(state): State<AppState>,
+    Json(request): Json<LogFilter>,
+) -> Result<Json<LogFilter>, ApiError> {
+    let filter = state
+        .live_config
+        .set_log_filter(&request.filter)
+        .map_err(ApiError::BadRequest)?;
+    tracing::warn!("Log filter set to {}", filter);
+
+    Ok(Json(LogFilter { filter }))
+}
+
+pub async fn admin_stats(State(state): State<AppState>) -> Result<Json<AuthStats>, ApiError> {
+    Ok(Json(auth_stats(&state.db).await?))
+}
+
+// Login activity of the last 30 days, charted with <meter> so no inline styles are needed
+pub async fn admin_dashboard(
+    State(state): State<AppState>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    user: UserProfile,
+) -> Result<Html<String>, ApiError> {
+    let stats = auth_stats(&state.db).await?;
+
+    let mut days: BTreeMap<NaiveDate, (i64, i64, i64)> = BTreeMap::new();
+    for row in &stats.daily {
+        let day = days.entry(row.period).or_default();
+        day.0 += row.logins;
+        day.1 += row.signups;
+        day.2 += row.failures;
+    }
+    let max = days
+        .values()
+        .map(|(logins, _, failures)| logins + failures)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let rows = days
+        .iter()
+        .map(|(day, (logins, signups, failures))| {
+            format!(
+                r#"<tr>
+                    <td>{}</td>
+                    <td><meter value="{}" max="{}"></meter></td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>"#,
+                day, logins, max, logins, signups, failures
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let providers = stats
+        .weekly
+        .iter()
+        .map(|row| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                row.period,
+                escape_html(&row.provider),
+                row.logins,
+                row.signups,
+                row.failures
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Admin Dashboard</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: #f4f5fb;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 900px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.1);
+                }}
+                .summary {{
+                    display: flex;
+                    gap: 20px;
+                }}
+                .card {{
+                    flex: 1;
+                    background-color: #f0f8ff;
+                    padding: 20px;
+                    border-radius: 5px;
+                }}
+                table {{
+                    width: 100%;
+                    border-collapse: collapse;
+                    margin: 20px 0;
+                }}
+                td, th {{
+                    text-align: left;
+                    padding: 4px 8px;
+                }}
+                meter {{
+                    width: 100%;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Authentication</h1>
+                <div class="summary">
+                    <div class="card"><h2>{}</h2>Active sessions</div>
+                    <div class="card"><h2>{:.1}%</h2>Failed logins (30 days)</div>
+                </div>
+                <h2>Daily logins</h2>
+                <table>
+                    <tr><th>Day</th><th></th><th>Logins</th><th>Signups</th><th>Failures</th></tr>
+                    {}
+                </table>
+                <h2>Weekly by provider</h2>
+                <table>
+                    <tr><th>Week</th><th>Provider</th><th>Logins</th><th>Signups</th><th>Failures</th></tr>
+                    {}
+                </table>
+                <h2>Force logout</h2>
+                <form method="post" action="/api/v1/admin/dashboard/force-logout">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <input type="email" name="email" placeholder="user@example.com" required>
+                    <select name="block_minutes">
+                        <option value="0">Don't block sign-in</option>
+                        <option value="15">Block sign-in for 15 minutes</option>
+                        <option value="60">Block sign-in for 1 hour</option>
+                        <option value="1440">Block sign-in for 24 hours</option>
+                    </select>
+                    <button type="submit">End all sessions</button>
+                </form>
+            </div>
+        </body>
+        </html>
+        "#,
+        stats.active_sessions,
+        stats.failure_rate * 100.0,
+        rows,
+        providers,
+        escape_html(user.csrf_token().unwrap_or_default()),
+        nonce = nonce
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForceLogoutForm {
+    pub csrf_token: String,
+    pub email: String,
+    pub block_minutes: i64,
+}
+
+// The dashboard's force-logout button, by email since the dashboard lists no users
+pub async fn force_logout_form(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Form(form): Form<ForceLogoutForm>,
+) -> Result<Redirect, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let target: Option<(i32,)> = sqlx::query_as("SELECT id FROM users WHERE email = $1")
+        .bind(form.email.trim())
+        .fetch_optional(&state.db)
+        .await?;
+    let Some((id,)) = target else {
+        return Err(ApiError::BadRequest(format!(
+            "No user with email {}",
+            form.email
+        )));
+    };
+
+    // Deleted users have no sessions to end
+    let Some(result) = force_logout(&state.db, id, block_duration(form.block_minutes)).await?
+    else {
+        return Err(ApiError::BadRequest(format!(
+            "No user with email {}",
+            form.email
+        )));
+    };
+    tracing::warn!(
+        "Admin {} forced logout of user {}: {:?}",
+        user.id,
+        id,
+        result
+    );
+
+    Ok(Redirect::to("/api/v1/admin/dashboard"))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewLegalDocument {
+    pub kind: String,
+    pub version: String,
+    pub content: String,
+}
+
+// Publishing a new version makes every user accept it again before reaching /protected
+pub async fn publish_legal_document(
+    State(state): State<AppState>,
+    Json(document): Json<NewLegalDocument>,
+) -> Result<(StatusCode, Json<LegalDocument>), ApiError> {
+    if !["terms", "privacy"].contains(&document.kind.as_str()) {
+        return Err(ApiError::BadRequest(
+            "kind must be terms or privacy".to_string(),
+        ));
+    }
+
+    let document = publish_document(
+        &state.db,
+        &document.kind,
+        &document.version,
+        &document.content,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(document)))
+}
@@ -1,20 +1,39 @@
 use axum::{
     extract::{Query, State},
-    response::{IntoResponse, Redirect},
-    Extension,
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension, Form,
 };
-use axum_extra::extract::cookie::PrivateCookieJar;
-use oauth2::{reqwest::async_http_client, AuthorizationCode, PkceCodeChallenge, TokenResponse};
+use axum_extra::extract::cookie::{CookieJar, PrivateCookieJar};
+use oauth2::{
+    basic::BasicTokenResponse, reqwest::async_http_client, AuthorizationCode, PkceCodeChallenge,
+    TokenResponse,
+};
+use serde::Deserialize;
+use tracing::Instrument;
 
 use crate::errors::ApiError;
-use crate::oauth::{AuthRequest, GoogleUserInfo, OAuthClients, PkceVerifiers, TwitterUserInfo};
-use crate::services::session::store_user_session;
+use crate::handlers::html::{escape_html, logout_form};
+use crate::handlers::{CallbackOrigin, DetailedUser, LoginContext, MaybeUser, UserProfile};
+use crate::middleware::CspNonce;
+use crate::oauth::{
+    AuthRequest, GoogleUserInfo, OAuthClients, PkceVerifiers, Provider, ProviderProfile,
+    TwitterUserInfo,
+};
+use crate::services::analytics;
+use crate::services::email_policy::is_disposable_email;
+use crate::services::providers::ensure_enabled;
+use crate::services::session::{store_identity_tokens, store_user_session};
+use crate::services::throttle::{email_key, ip_key};
 use crate::state::AppState;
 
 pub async fn twitter_login(
+    State(state): State<AppState>,
+    origin: CallbackOrigin,
     Extension(oauth_clients): Extension<OAuthClients>,
     Extension(pkce_verifiers): Extension<PkceVerifiers>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_enabled(&state.db, Provider::Twitter).await?;
+
     // Generate PKCE challenge
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
@@ -25,79 +44,682 @@ pub async fn twitter_login(
         pkce_verifier.secret().clone(),
     );
 
+    let settings = &state.settings.twitter;
+
     // Generate authorization URL with PKCE
-    let (auth_url, _) = oauth_clients
+    let mut request = oauth_clients
         .twitter
         .authorize_url(oauth2::CsrfToken::new_random)
-        .add_scope(oauth2::Scope::new("tweet.read".to_string()))
-        .add_scope(oauth2::Scope::new("users.read".to_string()))
-        .set_pkce_challenge(pkce_challenge)
-        .url();
+        .set_redirect_uri(origin.redirect_url(Provider::Twitter))
+        .add_scopes(settings.scopes())
+        .set_pkce_challenge(pkce_challenge);
+
+    // Twitter issues refresh tokens for the offline.access scope
+    if settings.offline_access {
+        request = request.add_scope(oauth2::Scope::new("offline.access".to_string()));
+    }
+
+    let (auth_url, _) = request.url();
+
+    Ok(Redirect::to(auth_url.as_str()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginOptions {
+    // Pre-fills the account chooser, e.g. user@acme.com
+    pub login_hint: Option<String>,
+    // Space-separated OpenID Connect prompt values, e.g. select_account
+    pub prompt: Option<String>,
+}
+
+// Prompt values Google accepts on the interactive flow; none belongs to /google_silent
+const LOGIN_PROMPTS: [&str; 2] = ["consent", "select_account"];
+
+pub async fn google_login(
+    State(state): State<AppState>,
+    origin: CallbackOrigin,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    Query(options): Query<LoginOptions>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_enabled(&state.db, Provider::Google).await?;
+
+    let settings = &state.settings.google;
+
+    let mut request = oauth_clients
+        .google
+        .authorize_url(oauth2::CsrfToken::new_random)
+        .set_redirect_uri(origin.redirect_url(Provider::Google))
+        .add_scopes(settings.scopes());
+
+    if settings.offline_access {
+        request = request.add_extra_param("access_type", "offline");
+    }
+
+    // A caller-supplied prompt replaces the configured one
+    match options.prompt {
+        Some(prompt) => {
+            if !prompt
+                .split(' ')
+                .all(|value| LOGIN_PROMPTS.contains(&value))
+            {
+                return Err(ApiError::BadRequest(format!(
+                    "Unsupported prompt {}",
+                    prompt
+                )));
+            }
+            request = request.add_extra_param("prompt", prompt);
+        }
+        None if settings.prompt_consent => {
+            request = request.add_extra_param("prompt", "consent");
+        }
+        None => {}
+    }
+
+    if let Some(login_hint) = options.login_hint {
+        request = request.add_extra_param("login_hint", login_hint);
+    }
 
-    Redirect::to(auth_url.as_str())
+    // Steer the account chooser to the Workspace domain when only one is allowed
+    if let [domain] = state.settings.google_allowed_domains.as_slice() {
+        request = request.add_extra_param("hd", domain.clone());
+    }
+
+    let (auth_url, _) = request.url();
+
+    Ok(Redirect::to(auth_url.as_str()))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn google_callback(
     State(state): State<AppState>,
+    origin: CallbackOrigin,
+    context: LoginContext,
     jar: PrivateCookieJar,
     Query(query): Query<AuthRequest>,
     Extension(oauth_clients): Extension<OAuthClients>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    MaybeUser(user): MaybeUser,
+) -> Result<Response, ApiError> {
+    // Additional scopes for a signed-in user are stored on their identity, not a new login
+    if query
+        .state
+        .as_deref()
+        .is_some_and(|state| state.starts_with(GRANT_STATE_PREFIX))
+    {
+        let user = user.ok_or(ApiError::Unauthorized)?;
+        let code = query.code()?;
+        return complete_google_grant(&state, &oauth_clients, &origin, &user, code).await;
+    }
+
+    let silent = query
+        .state
+        .as_deref()
+        .is_some_and(|state| state.starts_with(SILENT_STATE_PREFIX));
+
+    let result = match query.code() {
+        Ok(code) => {
+            complete_google_login(&state, context, jar, code, &oauth_clients, &origin).await
+        }
+        Err(e) => Err(e),
+    };
+
+    if result.is_err() {
+        analytics::record_failure(&state.db, Provider::Google).await;
+    }
+
+    // Silent attempts run in an iframe and report back to the opener page
+    if silent {
+        let status = match &result {
+            Ok(_) => "ok",
+            Err(_) => silent_error_status(query.error.as_deref()),
+        };
+        let page = silent_auth_result(&state, &nonce, status);
+
+        return Ok(match result {
+            Ok((jar, _)) => (jar, page).into_response(),
+            Err(_) => page.into_response(),
+        });
+    }
+
+    Ok(result?.into_response())
+}
+
+async fn complete_google_login(
+    state: &AppState,
+    context: LoginContext,
+    jar: PrivateCookieJar,
+    code: String,
+    oauth_clients: &OAuthClients,
+    origin: &CallbackOrigin,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
+    let ip = ip_key(context.ip);
+    state.throttle.check(&ip).await?;
+
+    let result = fetch_google_profile(state, oauth_clients, origin, code).await;
+    state.alerts.track_provider(Provider::Google, &result);
+
+    let (profile, token) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            state.throttle.record_failure(&ip).await;
+            return Err(e);
+        }
+    };
+
+    sign_in_google(state, context, jar, profile, Some(&token)).await
+}
+
+// Admission checks shared by the redirect flow and One Tap, followed by the session itself
+#[tracing::instrument(level = "debug", skip_all)]
+async fn sign_in_google(
+    state: &AppState,
+    context: LoginContext,
+    jar: PrivateCookieJar,
+    profile: GoogleUserInfo,
+    token: Option<&BasicTokenResponse>,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
+    let ip = ip_key(context.ip);
+    let email = email_key(&profile.email);
+    state.throttle.check(&email).await?;
+
+    if profile.email_verified == Some(false) {
+        state.throttle.record_failure(&ip).await;
+        state.throttle.record_failure(&email).await;
+        return Err(ApiError::LoginRejected(format!(
+            "{} has not been verified by Google.",
+            profile.email
+        )));
+    }
+
+    // The hd parameter is only a hint, so the claim itself must be verified
+    if !google_domain_allowed(state, &profile) {
+        state.throttle.record_failure(&ip).await;
+        state.throttle.record_failure(&email).await;
+        return Err(ApiError::LoginRejected(format!(
+            "{} is not part of an allowed Google Workspace domain.",
+            profile.email
+        )));
+    }
+
+    if is_disposable_email(&state.db, &state.settings, &profile.email).await? {
+        state.throttle.record_failure(&ip).await;
+        state.throttle.record_failure(&email).await;
+        return Err(ApiError::LoginRejected(
+            "Sign-ins from disposable email addresses are not allowed.".to_string(),
+        ));
+    }
+
+    let claims = serde_json::to_value(&profile).unwrap_or_default();
+    let profile = ProviderProfile {
+        provider: Provider::Google,
+        subject: profile.sub,
+        email: profile.email,
+        display_name: profile.name,
+        claims,
+    };
+
+    // Store session
+    sign_in(state, jar, profile, context, token).await
+}
+
+// Store the session and feed the throttle with the outcome. A rejected login counts
+// against both the client and the account; a successful one clears only that
+// account's failures, so logging into one account doesn't reset the back-off a
+// client earned guessing at others.
+async fn sign_in(
+    state: &AppState,
+    jar: PrivateCookieJar,
+    profile: ProviderProfile,
+    context: LoginContext,
+    token: Option<&BasicTokenResponse>,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
+    let ip = ip_key(context.ip);
+    let email = email_key(&profile.email);
+
+    let result = store_user_session(state, jar, profile, context, token).await;
+    match &result {
+        Ok(_) => state.throttle.record_success(&email).await,
+        Err(ApiError::LoginRejected(_) | ApiError::AccountNotFound(_)) => {
+            state.throttle.record_failure(&ip).await;
+            state.throttle.record_failure(&email).await;
+        }
+        Err(_) => {}
+    }
+
+    result
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OneTapForm {
+    pub credential: String,
+    pub g_csrf_token: String,
+}
+
+// Google Identity Services posts the One Tap credential (a signed ID token) here
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn google_one_tap(
+    State(state): State<AppState>,
+    context: LoginContext,
+    cookies: CookieJar,
+    jar: PrivateCookieJar,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    Form(form): Form<OneTapForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !state.settings.google_one_tap {
+        return Err(ApiError::BadRequest(
+            "One Tap sign-in is disabled".to_string(),
+        ));
+    }
+
+    // Double-submit check: the library sets the same token as a cookie and a form field
+    if cookies.get("g_csrf_token").map(|cookie| cookie.value()) != Some(&form.g_csrf_token) {
+        return Err(ApiError::BadRequest(
+            "Invalid One Tap CSRF token".to_string(),
+        ));
+    }
+
+    let result = complete_one_tap(&state, context, jar, &oauth_clients, &form.credential).await;
+
+    if result.is_err() {
+        analytics::record_failure(&state.db, Provider::Google).await;
+    }
+
+    result
+}
+
+async fn complete_one_tap(
+    state: &AppState,
+    context: LoginContext,
+    jar: PrivateCookieJar,
+    oauth_clients: &OAuthClients,
+    credential: &str,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
+    let ip = ip_key(context.ip);
+    state.throttle.check(&ip).await?;
+
+    let client_id = oauth_clients.google.client_id().as_str();
+    let profile = match state
+        .google_keys
+        .verify(&state.ctx, credential, client_id)
+        .instrument(tracing::debug_span!("id_token_verify"))
+        .await
+    {
+        Ok(profile) => profile,
+        Err(e) => {
+            state.throttle.record_failure(&ip).await;
+            return Err(e);
+        }
+    };
+
+    sign_in_google(state, context, jar, profile, None).await
+}
+
+// Start a prompt=none login meant to be loaded in a hidden iframe. Google either
+// redirects straight back with a code or reports that interaction is required.
+pub async fn google_silent_login(
+    State(state): State<AppState>,
+    origin: CallbackOrigin,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    user: MaybeUser,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_enabled(&state.db, Provider::Google).await?;
+    let user = user.with_details(&state.db).await?;
+
+    let mut request = oauth_clients
+        .google
+        .authorize_url(|| {
+            let random = oauth2::CsrfToken::new_random();
+            oauth2::CsrfToken::new(format!("{}{}", SILENT_STATE_PREFIX, random.secret()))
+        })
+        .set_redirect_uri(origin.redirect_url(Provider::Google))
+        .add_scopes(state.settings.google.scopes())
+        .add_extra_param("prompt", "none");
+
+    // Renew the account that is already signed in
+    if let Some(user) = user {
+        request = request.add_extra_param("login_hint", user.email);
+    }
+
+    if let [domain] = state.settings.google_allowed_domains.as_slice() {
+        request = request.add_extra_param("hd", domain.clone());
+    }
+
+    let (auth_url, _) = request.url();
+
+    Ok(Redirect::to(auth_url.as_str()))
+}
+
+// Marks the OAuth state of silent attempts so the callback answers with a postMessage page
+const SILENT_STATE_PREFIX: &str = "silent:";
+// Marks the OAuth state of incremental authorization requests
+const GRANT_STATE_PREFIX: &str = "grant:";
+
+#[derive(Debug, Deserialize)]
+pub struct ScopeRequest {
+    // Space-separated Google scopes, e.g. https://www.googleapis.com/auth/calendar.readonly
+    pub scope: String,
+}
+
+// Ask a signed-in user for additional Google scopes. include_granted_scopes makes
+// the new token cover everything granted so far, so login itself can stay minimal.
+pub async fn google_grant_scopes(
+    Extension(oauth_clients): Extension<OAuthClients>,
+    origin: CallbackOrigin,
+    DetailedUser(user): DetailedUser,
+    Query(query): Query<ScopeRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let google = Provider::Google.as_str();
+    if !user
+        .identities
+        .iter()
+        .any(|identity| identity.provider == google)
+    {
+        return Err(ApiError::BadRequest(
+            "Additional Google scopes require a linked Google account".to_string(),
+        ));
+    }
+
+    let scopes = query
+        .scope
+        .split_whitespace()
+        .map(|scope| oauth2::Scope::new(scope.to_string()));
+
+    let (auth_url, _) = oauth_clients
+        .google
+        .authorize_url(|| {
+            let random = oauth2::CsrfToken::new_random();
+            oauth2::CsrfToken::new(format!("{}{}", GRANT_STATE_PREFIX, random.secret()))
+        })
+        .set_redirect_uri(origin.redirect_url(Provider::Google))
+        .add_scopes(scopes)
+        .add_extra_param("include_granted_scopes", "true")
+        .add_extra_param("login_hint", user.email)
+        .url();
+
+    Ok(Redirect::to(auth_url.as_str()))
+}
+
+async fn complete_google_grant(
+    state: &AppState,
+    oauth_clients: &OAuthClients,
+    origin: &CallbackOrigin,
+    user: &UserProfile,
+    code: String,
+) -> Result<Response, ApiError> {
+    let (profile, token) = fetch_google_profile(state, oauth_clients, origin, code).await?;
+
+    let claims = serde_json::to_value(&profile).unwrap_or_default();
+    let profile = ProviderProfile {
+        provider: Provider::Google,
+        subject: profile.sub,
+        email: profile.email,
+        display_name: profile.name,
+        claims,
+    };
+
+    // The grant must come from a Google account already linked to this user
+    if !store_identity_tokens(&state.db, user.id, &profile, &token).await? {
+        return Err(ApiError::BadRequest(
+            "The Google account does not match the signed-in user".to_string(),
+        ));
+    }
+
+    Ok(Redirect::to("/protected/profile").into_response())
+}
+
+// Pass through Google's error codes (login_required, consent_required, ...) but nothing else
+fn silent_error_status(error: Option<&str>) -> &str {
+    match error {
+        Some(error) if error.chars().all(|c| c.is_ascii_lowercase() || c == '_') => error,
+        _ => "error",
+    }
+}
+
+fn silent_auth_result(state: &AppState, nonce: &str, status: &str) -> Html<String> {
+    Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <body>
+            <script nonce="{}">
+                window.parent.postMessage({{ type: "silent_auth", status: "{}" }}, "{}");
+            </script>
+        </body>
+        </html>
+        "#,
+        nonce,
+        status,
+        state.settings.public_origin()
+    ))
+}
+
+fn google_domain_allowed(state: &AppState, profile: &GoogleUserInfo) -> bool {
+    let allowed = &state.settings.google_allowed_domains;
+
+    allowed.is_empty()
+        || profile
+            .hd
+            .as_deref()
+            .is_some_and(|hd| allowed.iter().any(|domain| domain.eq_ignore_ascii_case(hd)))
+}
+
+async fn fetch_google_profile(
+    state: &AppState,
+    oauth_clients: &OAuthClients,
+    origin: &CallbackOrigin,
+    code: String,
+) -> Result<(GoogleUserInfo, BasicTokenResponse), ApiError> {
     // Exchange the authorization code for an access token
     let token = oauth_clients
         .google
-        .exchange_code(AuthorizationCode::new(query.code))
+        .exchange_code(AuthorizationCode::new(code))
+        .set_redirect_uri(origin.redirect_url(Provider::Google))
         .request_async(async_http_client)
+        .instrument(tracing::debug_span!("code_exchange"))
         .await?;
 
     // Use the access token to get user info
-    let profile = state
-        .ctx
-        .get("https://openidconnect.googleapis.com/v1/userinfo")
-        .bearer_auth(token.access_token().secret().to_owned())
-        .send()
-        .await?
-        .json::<GoogleUserInfo>()
-        .await?;
+    let profile = async {
+        state
+            .ctx
+            .get("https://openidconnect.googleapis.com/v1/userinfo")
+            .bearer_auth(token.access_token().secret().to_owned())
+            .send()
+            .await?
+            .json::<GoogleUserInfo>()
+            .await
+    }
+    .instrument(tracing::debug_span!("userinfo_fetch"))
+    .await?;
 
-    // Store session
-    store_user_session(State(state), jar, profile.email, token).await
+    Ok((profile, token))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn twitter_callback(
     State(state): State<AppState>,
+    origin: CallbackOrigin,
+    context: LoginContext,
     jar: PrivateCookieJar,
     Query(query): Query<AuthRequest>,
     Extension(oauth_clients): Extension<OAuthClients>,
     Extension(pkce_verifiers): Extension<PkceVerifiers>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let result = match query.code() {
+        Ok(code) => {
+            complete_twitter_login(
+                &state,
+                context,
+                jar,
+                code,
+                &oauth_clients,
+                &pkce_verifiers,
+                &origin,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+
+    if result.is_err() {
+        analytics::record_failure(&state.db, Provider::Twitter).await;
+    }
+
+    result
+}
+
+async fn complete_twitter_login(
+    state: &AppState,
+    context: LoginContext,
+    jar: PrivateCookieJar,
+    code: String,
+    oauth_clients: &OAuthClients,
+    pkce_verifiers: &PkceVerifiers,
+    origin: &CallbackOrigin,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
+    let ip = ip_key(context.ip);
+    state.throttle.check(&ip).await?;
+
+    let result = fetch_twitter_profile(state, oauth_clients, pkce_verifiers, origin, code).await;
+    state.alerts.track_provider(Provider::Twitter, &result);
+
+    let (profile, token) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            state.throttle.record_failure(&ip).await;
+            return Err(e);
+        }
+    };
+
+    // Use Twitter username as email (Twitter doesn't provide email in v2 API easily)
+    let email = format!("{}@twitter.local", profile.data.username);
+
+    state.throttle.check(&email_key(&email)).await?;
+
+    let claims = serde_json::to_value(&profile.data).unwrap_or_default();
+    let profile = ProviderProfile {
+        provider: Provider::Twitter,
+        subject: profile.data.id,
+        email,
+        display_name: Some(profile.data.name),
+        claims,
+    };
+
+    // Store session
+    sign_in(state, jar, profile, context, Some(&token)).await
+}
+
+async fn fetch_twitter_profile(
+    state: &AppState,
+    oauth_clients: &OAuthClients,
+    pkce_verifiers: &PkceVerifiers,
+    origin: &CallbackOrigin,
+    code: String,
+) -> Result<(TwitterUserInfo, BasicTokenResponse), ApiError> {
     // Retrieve the PKCE verifier
-    let mut verifiers = pkce_verifiers.lock().await;
-    let pkce_verifier = verifiers
-        .remove("twitter_verifier")
+    let pkce_verifier = async { pkce_verifiers.lock().await.remove("twitter_verifier") }
+        .instrument(tracing::debug_span!("state_lookup"))
+        .await
         .ok_or_else(|| ApiError::BadRequest("Missing PKCE verifier".to_string()))?;
 
     // Exchange the authorization code for an access token with PKCE
     let token = oauth_clients
         .twitter
-        .exchange_code(AuthorizationCode::new(query.code))
+        .exchange_code(AuthorizationCode::new(code))
+        .set_redirect_uri(origin.redirect_url(Provider::Twitter))
         .set_pkce_verifier(oauth2::PkceCodeVerifier::new(pkce_verifier))
         .request_async(async_http_client)
+        .instrument(tracing::debug_span!("code_exchange"))
         .await?;
 
     // Use the access token to get user info from Twitter
-    let profile = state
-        .ctx
-        .get("https://api.twitter.com/2/users/me")
-        .bearer_auth(token.access_token().secret().to_owned())
-        .send()
-        .await?
-        .json::<TwitterUserInfo>()
-        .await?;
+    let profile = async {
+        state
+            .ctx
+            .get("https://api.twitter.com/2/users/me")
+            .bearer_auth(token.access_token().secret().to_owned())
+            .send()
+            .await?
+            .json::<TwitterUserInfo>()
+            .await
+    }
+    .instrument(tracing::debug_span!("userinfo_fetch"))
+    .await?;
 
-    // Use Twitter username as email (Twitter doesn't provide email in v2 API easily)
-    let email = format!("{}@twitter.local", profile.data.username);
+    Ok((profile, token))
+}
 
-    // Store session
-    store_user_session(State(state), jar, email, token).await
+// Confirmation page for links to /api/auth/logout; the logout itself is a POST
+pub async fn logout_page(
+    State(state): State<AppState>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    user: MaybeUser,
+) -> Result<Response, ApiError> {
+    let Some(user) = user.with_details(&state.db).await? else {
+        return Ok(Redirect::to("/").into_response());
+    };
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Logout</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 500px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                    text-align: center;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    text-decoration: none;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                    margin: 10px;
+                }}
+                .button.logout {{
+                    background-color: #dc3545;
+                }}
+                .inline {{
+                    display: inline;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Log out?</h1>
+                <p>You are signed in as <strong>{}</strong>.</p>
+                {}
+                <form method="post" action="/api/auth/logout" class="inline">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <input type="hidden" name="everywhere" value="true">
+                    <button type="submit" class="button logout">Log out everywhere</button>
+                </form>
+                <a href="/protected" class="button">Cancel</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        escape_html(user.name()),
+        logout_form(user.csrf_token().unwrap_or_default()),
+        escape_html(user.csrf_token().unwrap_or_default()),
+        nonce = nonce
+    ))
+    .into_response())
 }
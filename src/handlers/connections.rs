@@ -0,0 +1,139 @@
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::{Extension, Form};
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::{RevokeForm, UserProfile};
+use crate::middleware::CspNonce;
+use crate::oauth::{OAuthClients, Provider};
+use crate::services::provider_tokens::{list_connections, revoke_connection};
+use crate::state::AppState;
+
+// Provider accounts linked to the user and the API access the app holds for each
+pub async fn connections_page(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+) -> Result<Html<String>, ApiError> {
+    let connections = list_connections(&state.db, user.id).await?;
+
+    let rows = connections
+        .iter()
+        .map(|connection| {
+            let provider = Provider::parse(&connection.provider)
+                .map_or(connection.provider.as_str(), |provider| provider.label());
+
+            // Only identities with stored tokens have anything to revoke
+            let action = if connection.has_tokens {
+                format!(
+                    r#"<form method="post" action="/protected/connections/{}/revoke">
+                            <input type="hidden" name="csrf_token" value="{}">
+                            <button type="submit" class="button revoke">Revoke access</button>
+                        </form>"#,
+                    connection.id,
+                    escape_html(user.csrf_token().unwrap_or_default())
+                )
+            } else {
+                "Sign-in only".to_string()
+            };
+
+            format!(
+                r#"<tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>"#,
+                escape_html(provider),
+                escape_html(&connection.scopes.join(", ")),
+                connection
+                    .created_at
+                    .map_or(String::new(), |at| at.format("%Y-%m-%d").to_string()),
+                connection.last_login_at.map_or(String::new(), |at| at
+                    .format("%Y-%m-%d %H:%M UTC")
+                    .to_string()),
+                action
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Connected Accounts</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 800px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                table {{
+                    width: 100%;
+                    margin: 20px 0;
+                    text-align: left;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    text-decoration: none;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                    margin: 10px 0;
+                }}
+                .button.revoke {{
+                    background-color: #dc3545;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Connected Accounts</h1>
+                <p>Revoking access stops this app from using your provider account on your
+                   behalf. You can still sign in with it.</p>
+                <table>
+                    <tr><th>Provider</th><th>Granted access</th><th>Linked</th><th>Last sign-in</th><th></th></tr>
+                    {}
+                </table>
+                <a href="/protected" class="button">Back</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        rows,
+        nonce = nonce
+    )))
+}
+
+pub async fn revoke_connection_access(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    Path(id): Path<i32>,
+    Form(form): Form<RevokeForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    revoke_connection(&state.db, &oauth_clients, user.id, id).await?;
+
+    Ok(Redirect::to("/protected/connections"))
+}
@@ -0,0 +1,40 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use serde_json::Value;
+
+use crate::errors::ApiError;
+use crate::handlers::ClientIp;
+use crate::services::csp_reports::{parse_reports, store_violation};
+use crate::state::AppState;
+
+// Reporting API batches can hold many reports; the rest of a large batch is dropped
+const MAX_REPORTS_PER_REQUEST: usize = 20;
+
+// Browsers post here when a page violates the Content-Security-Policy. Violations are
+// logged and aggregated in csp_reports for review in the admin API.
+pub async fn csp_report(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    body: String,
+) -> Result<StatusCode, ApiError> {
+    state.csp_report_limiter.check(ip).await?;
+
+    let body: Value = serde_json::from_str(&body)
+        .map_err(|_| ApiError::BadRequest("Invalid CSP report".to_string()))?;
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+
+    for violation in parse_reports(&body).iter().take(MAX_REPORTS_PER_REQUEST) {
+        tracing::info!(
+            "CSP violation on {}: {} blocked {}",
+            violation.document_uri,
+            violation.directive,
+            violation.blocked_uri
+        );
+        store_violation(&state.db, violation, user_agent).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
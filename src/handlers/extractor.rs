@@ -1,47 +1,373 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use axum::extract::{FromRequest, Request};
-
+use crate::config::settings::SessionMode;
 use crate::errors::ApiError;
+use crate::middleware::RequestUser;
+use crate::oauth::Provider;
+use crate::services::cookies::guest_cookie_name;
+use crate::services::fingerprint::session_fingerprint;
+use crate::services::preferences::{guest_session, PreferenceOwner};
+use crate::services::stateless_session::SessionClaims;
 use crate::state::AppState;
-use axum::extract::FromRequestParts;
+use axum::extract::{ConnectInfo, FromRequestParts, Host};
+use axum::http::{header, request::Parts};
 use axum_extra::extract::cookie::{Key, PrivateCookieJar};
+use oauth2::RedirectUrl;
+use sqlx::PgPool;
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct UserIdentity {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
 pub struct UserProfile {
+    pub id: i32,
     pub email: String,
+    pub display_name: Option<String>,
+    pub roles: Vec<String>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub last_login_provider: Option<String>,
+    // CSRF token of the current session, embedded in forms
+    #[serde(skip)]
+    pub csrf_token: Option<String>,
+    #[sqlx(skip)]
+    pub identities: Vec<UserIdentity>,
+    // Built from stateless claims: only id, roles and the CSRF token are filled in
+    #[serde(skip)]
+    #[sqlx(skip)]
+    partial: bool,
+}
+
+impl UserProfile {
+    // Load the user owning a valid, unexpired session. When the session is bound
+    // to a client fingerprint, it must match the current one.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn load(
+        db: &PgPool,
+        session_id: &str,
+        fingerprint: Option<&str>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let user = sqlx::query_as::<_, UserProfile>(
+            "SELECT users.id, users.email, users.display_name, users.roles,
+                    users.last_login_at, users.last_login_provider, sessions.csrf_token
+             FROM sessions
+             JOIN users ON sessions.user_id = users.id
+             WHERE sessions.session_id = $1 AND sessions.expires_at > NOW()
+               AND users.deleted_at IS NULL
+               AND ($2::text IS NULL OR sessions.fingerprint = $2)
+             LIMIT 1",
+        )
+        .bind(session_id)
+        .bind(fingerprint)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(mut user) = user else {
+            return Ok(None);
+        };
+
+        user.identities = load_identities(db, user.id).await?;
+
+        Ok(Some(user))
+    }
+
+    // User of a stateless session, from the cookie alone. Pages that render the
+    // profile fill in the rest with with_details.
+    pub fn from_claims(claims: SessionClaims) -> Self {
+        UserProfile {
+            id: claims.uid,
+            email: String::new(),
+            display_name: None,
+            roles: claims.roles,
+            last_login_at: None,
+            last_login_provider: None,
+            csrf_token: Some(claims.csrf_token),
+            identities: Vec::new(),
+            partial: true,
+        }
+    }
+
+    // Load the profile row and linked identities of a user built from claims, or
+    // None once the user is deleted. The roles and CSRF token stay the session's.
+    pub async fn with_details(self, db: &PgPool) -> Result<Option<Self>, sqlx::Error> {
+        if !self.partial {
+            return Ok(Some(self));
+        }
+
+        let user = sqlx::query_as::<_, UserProfile>(
+            "SELECT id, email, display_name, roles, last_login_at, last_login_provider,
+                    NULL::text AS csrf_token
+             FROM users
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(self.id)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(user) = user else {
+            return Ok(None);
+        };
+
+        Ok(Some(UserProfile {
+            roles: self.roles,
+            csrf_token: self.csrf_token,
+            identities: load_identities(db, self.id).await?,
+            ..user
+        }))
+    }
+
+    // Provider used for the most recent login
+    pub fn provider(&self) -> Option<Provider> {
+        self.last_login_provider
+            .as_deref()
+            .and_then(Provider::parse)
+    }
+
+    pub fn name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.email)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    // None for sessions without a token, whose form posts are all rejected
+    pub fn csrf_token(&self) -> Option<&str> {
+        self.csrf_token.as_deref()
+    }
+}
+
+async fn load_identities(db: &PgPool, user_id: i32) -> Result<Vec<UserIdentity>, sqlx::Error> {
+    sqlx::query_as::<_, UserIdentity>(
+        "SELECT provider, provider_user_id, created_at
+         FROM user_identities
+         WHERE user_id = $1
+         ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for UserProfile {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let MaybeUser(user) = MaybeUser::from_request_parts(parts, state).await?;
+        user.ok_or(ApiError::Unauthorized)
+    }
+}
+
+// Signed-in user with the profile row and identities loaded, for pages that render
+// them. Only differs from UserProfile in stateless mode.
+pub struct DetailedUser(pub UserProfile);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for DetailedUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = UserProfile::from_request_parts(parts, state).await?;
+        let user = user.with_details(&state.db).await?;
+        user.map(DetailedUser).ok_or(ApiError::Unauthorized)
+    }
+}
+
+// Like UserProfile, but yields None for anonymous visitors instead of rejecting
+pub struct MaybeUser(pub Option<UserProfile>);
+
+impl MaybeUser {
+    // See UserProfile::with_details
+    pub async fn with_details(self, db: &PgPool) -> Result<Option<UserProfile>, sqlx::Error> {
+        match self.0 {
+            Some(user) => user.with_details(db).await,
+            None => Ok(None),
+        }
+    }
 }
 
 #[axum::async_trait]
-impl FromRequest<AppState> for UserProfile {
+impl FromRequestParts<AppState> for MaybeUser {
     type Rejection = ApiError;
 
-    async fn from_request(req: Request, state: &AppState) -> Result<Self, Self::Rejection> {
-        let (mut parts, _body) = req.into_parts();
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let jar: PrivateCookieJar<Key> = PrivateCookieJar::from_request_parts(parts, state)
+            .await
+            .map_err(|_| ApiError::Unauthorized)?;
+
+        let Some(cookie) = jar
+            .get(&state.settings.session_cookie_name)
+            .map(|cookie| cookie.value().to_owned())
+        else {
+            return Ok(MaybeUser(None));
+        };
+
+        let context = LoginContext::from_request_parts(parts, state).await?;
+        let fingerprint = session_fingerprint(&state.settings, &context);
+
+        let user = match state.settings.session_mode {
+            SessionMode::Database => {
+                UserProfile::load(&state.db, &cookie, fingerprint.as_deref()).await?
+            }
+            SessionMode::Stateless => {
+                let claims = SessionClaims::decode(&cookie)
+                    .filter(|claims| claims.matches(fingerprint.as_deref()))
+                    .filter(|claims| !state.revoked_sessions.is_revoked(&claims.sid));
+                claims.map(UserProfile::from_claims)
+            }
+        };
+
+        // Let the access log know who made the request
+        if let (Some(user), Some(request_user)) = (&user, parts.extensions.get::<RequestUser>()) {
+            request_user.set(user.id);
+        }
+
+        Ok(MaybeUser(user))
+    }
+}
+
+// Signed-in user, or else the guest session from the guest cookie
+#[axum::async_trait]
+impl FromRequestParts<AppState> for PreferenceOwner {
+    type Rejection = ApiError;
 
-        let jar: PrivateCookieJar<Key> = PrivateCookieJar::from_request_parts(&mut parts, state)
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let MaybeUser(Some(user)) = MaybeUser::from_request_parts(parts, state).await? {
+            return Ok(PreferenceOwner::User(user.id));
+        }
+
+        let jar: PrivateCookieJar<Key> = PrivateCookieJar::from_request_parts(parts, state)
             .await
             .map_err(|_| ApiError::Unauthorized)?;
 
-        let Some(cookie) = jar.get("sid").map(|cookie| cookie.value().to_owned()) else {
+        let Some(cookie) = jar.get(&guest_cookie_name(&state.settings)) else {
             return Err(ApiError::Unauthorized);
         };
 
-        let user = sqlx::query_as::<_, UserProfile>(
-            "SELECT users.email
-             FROM sessions
-             LEFT JOIN users ON sessions.user_id = users.id
-             WHERE sessions.session_id = $1 AND sessions.expires_at > NOW()
-             LIMIT 1",
-        )
-        .bind(cookie)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => ApiError::Unauthorized,
-            _ => ApiError::Database(e),
-        })?;
-
-        Ok(user)
+        guest_session(&state.db, cookie.value())
+            .await?
+            .map(PreferenceOwner::Guest)
+            .ok_or(ApiError::Unauthorized)
+    }
+}
+
+// Address of the client, taken from X-Forwarded-For when the peer is a trusted proxy
+pub struct ClientIp(pub IpAddr);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() else {
+            return Err(ApiError::BadRequest("Missing client address".to_string()));
+        };
+
+        let trusted = &state.settings.trusted_proxies;
+        let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+
+        let hops: Vec<IpAddr> = parts
+            .headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+
+        // Walk back through the proxy chain until the first untrusted hop
+        let mut ip = peer.ip();
+        for hop in hops.iter().rev() {
+            if !is_trusted(&ip) {
+                break;
+            }
+            ip = *hop;
+        }
+
+        Ok(ClientIp(ip))
+    }
+}
+
+// Origin OAuth redirect URIs are built on: the request's host when it is one of
+// CALLBACK_ORIGINS, so dev, staging and preview hosts each get their own callback
+#[derive(Debug, Clone)]
+pub struct CallbackOrigin(pub String);
+
+impl CallbackOrigin {
+    // The same URI must be sent when authorizing and when exchanging the code
+    pub fn redirect_url(&self, provider: Provider) -> Cow<'static, RedirectUrl> {
+        Cow::Owned(provider.redirect_url(&self.0))
+    }
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for CallbackOrigin {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let host = Host::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|Host(host)| host);
+
+        Ok(CallbackOrigin(
+            state.settings.callback_origin(host.as_deref()).to_string(),
+        ))
+    }
+}
+
+// Client details recorded with each new session
+#[derive(Debug, Clone)]
+pub struct LoginContext {
+    pub ip: IpAddr,
+    pub user_agent: String,
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for LoginContext {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .chars()
+            .take(512)
+            .collect();
+
+        Ok(LoginContext { ip, user_agent })
     }
 }
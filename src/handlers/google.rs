@@ -0,0 +1,27 @@
+use axum::extract::State;
+use axum::{Extension, Json};
+
+use crate::errors::ApiError;
+use crate::handlers::UserProfile;
+use crate::oauth::{OAuthClients, Provider};
+use crate::services::google_api::{upcoming_events, CalendarEvent};
+use crate::services::provider_tokens::IdentityToken;
+use crate::state::AppState;
+
+// Server-side proxy so Google tokens never reach the browser. The scope is granted
+// through /api/auth/google_scopes?scope=https://www.googleapis.com/auth/calendar.readonly
+pub async fn calendar_events(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(oauth_clients): Extension<OAuthClients>,
+) -> Result<Json<Vec<CalendarEvent>>, ApiError> {
+    let mut identity = IdentityToken::load(&state.db, user.id, Provider::Google)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest("No Google account is linked".to_string()))?;
+
+    let access_token = identity
+        .access_token(&state.db, &oauth_clients.google)
+        .await?;
+
+    Ok(Json(upcoming_events(&state.ctx, &access_token).await?))
+}
@@ -1,7 +1,13 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde_json::json;
 
 use crate::errors::ApiError;
+use crate::services::schema::{applied_version, latest_version};
 use crate::state::AppState;
 
 pub async fn health_check(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
@@ -23,3 +29,38 @@ pub async fn health_check(State(state): State<AppState>) -> Result<impl IntoResp
 
     Ok((StatusCode::OK, Json(health)))
 }
+
+// Readiness for load balancers: 503 until the database is reachable and has every
+// migration this build expects, e.g. while another replica is still migrating
+pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse {
+    let expected = latest_version();
+
+    match applied_version(&state.db).await {
+        Ok(Some(applied)) if applied >= expected => (
+            StatusCode::OK,
+            Json(json!({ "status": "ready", "schema_version": applied })),
+        ),
+        Ok(applied) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": "migrating",
+                "schema_version": applied,
+                "expected_schema_version": expected
+            })),
+        ),
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "unavailable", "database": "disconnected" })),
+        ),
+    }
+}
+
+// Prometheus metrics for the connection pool; reachable from ADMIN_ALLOWED_CIDRS only
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state
+            .pool_metrics
+            .render(&state.db, state.settings.database_max_connections),
+    )
+}
@@ -1,16 +1,72 @@
+use axum::extract::State;
 use axum::response::Html;
 use axum::Extension;
+use axum_extra::extract::cookie::PrivateCookieJar;
 
-use crate::oauth::ClientIds;
+use crate::errors::ApiError;
+use crate::handlers::html::{escape_html, logout_form, one_tap_prompt};
+use crate::handlers::MaybeUser;
+use crate::middleware::CspNonce;
+use crate::oauth::{OAuthClients, Provider};
+use crate::services::cookies::last_provider_cookie_name;
+use crate::services::providers::enabled_providers;
+use crate::state::AppState;
 
-pub async fn homepage(Extension(client_ids): Extension<ClientIds>) -> Html<String> {
-    Html(format!(
+pub async fn homepage(
+    State(state): State<AppState>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    user: MaybeUser,
+    jar: PrivateCookieJar,
+) -> Result<Html<String>, ApiError> {
+    let user = user.with_details(&state.db).await?;
+    let enabled = enabled_providers(&state.db).await?;
+    let last_used = last_used_provider(&state, &jar);
+
+    // Signed-in visitors get a shortcut and logout instead of the login buttons
+    let actions = match user {
+        Some(user) => format!(
+            r#"<p class="signed-in">Signed in as <strong>{}</strong></p>
+                <div class="button-group">
+                    <a href="/protected" class="button protected">🔒 Continue</a>
+                    {}
+                </div>"#,
+            escape_html(user.name()),
+            logout_form(user.csrf_token().unwrap_or_default())
+        ),
+        None => format!(
+            r#"<div class="button-group">
+                    {}
+                    {}
+                </div>
+
+                <a href="/protected" class="button protected">🔒 Access Protected Area</a>
+                {}"#,
+            provider_button(
+                &enabled,
+                last_used,
+                Provider::Google,
+                "button google",
+                "Google"
+            ),
+            provider_button(
+                &enabled,
+                last_used,
+                Provider::Twitter,
+                "button twitter",
+                "Twitter"
+            ),
+            google_one_tap(&state, &enabled, &oauth_clients)
+        ),
+    };
+
+    Ok(Html(format!(
         r#"
         <!DOCTYPE html>
         <html>
         <head>
             <title>OAuth2 Demo</title>
-            <style>
+            <style nonce="{nonce}">
                 body {{
                     font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
                     margin: 0;
@@ -81,6 +137,26 @@ pub async fn homepage(Extension(client_ids): Extension<ClientIds>) -> Html<Strin
                     background-color: #5a67d8;
                     transform: translateY(-2px);
                 }}
+                .button.logout {{
+                    background-color: #dc3545;
+                    border: none;
+                    font: inherit;
+                    cursor: pointer;
+                }}
+                .inline {{
+                    display: inline;
+                }}
+                .icon {{
+                    margin-right: 8px;
+                }}
+                .last-used {{
+                    outline: 3px solid #ffd54f;
+                    outline-offset: 2px;
+                }}
+                .signed-in {{
+                    color: #333;
+                    margin-bottom: 20px;
+                }}
             </style>
         </head>
         <body>
@@ -88,44 +164,32 @@ pub async fn homepage(Extension(client_ids): Extension<ClientIds>) -> Html<Strin
                 <h1>🔐 OAuth Demo</h1>
                 <p class="subtitle">Secure OAuth2 authentication with Google and Twitter</p>
 
-                <div class="button-group">
-                    <a href="https://accounts.google.com/o/oauth2/v2/auth?scope=openid%20profile%20email&client_id={}&response_type=code&redirect_uri=http://localhost:8000/api/auth/google_callback"
-                       class="button google">
-                        <svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" style="margin-right: 8px;">
-                            <path d="M22.56 12.25c0-.78-.07-1.53-.2-2.25H12v4.26h5.92c-.26 1.37-1.04 2.53-2.21 3.31v2.77h3.57c2.08-1.92 3.28-4.74 3.28-8.09z"/>
-                            <path d="M12 23c2.97 0 5.46-.98 7.28-2.66l-3.57-2.77c-.98.66-2.23 1.06-3.71 1.06-2.86 0-5.29-1.93-6.16-4.53H2.18v2.84C3.99 20.53 7.7 23 12 23z"/>
-                            <path d="M5.84 14.09c-.22-.66-.35-1.36-.35-2.09s.13-1.43.35-2.09V7.07H2.18C1.43 8.55 1 10.22 1 12s.43 3.45 1.18 4.93l2.85-2.22.81-.62z"/>
-                            <path d="M12 5.38c1.62 0 3.06.56 4.21 1.64l3.15-3.15C17.45 2.09 14.97 1 12 1 7.7 1 3.99 3.47 2.18 7.07l3.66 2.84c.87-2.6 3.3-4.53 6.16-4.53z"/>
-                        </svg>
-                        Google
-                    </a>
-
-                    <a href="/api/auth/twitter_login"
-                       class="button twitter">
-                        <svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" style="margin-right: 8px;">
-                            <path d="M23.643 4.937c-.835.37-1.732.62-2.675.733.962-.576 1.7-1.49 2.048-2.578-.9.534-1.897.922-2.958 1.13-.85-.904-2.06-1.47-3.4-1.47-2.572 0-4.658 2.086-4.658 4.66 0 .364.042.718.12 1.06-3.873-.195-7.304-2.05-9.602-4.868-.4.69-.63 1.49-.63 2.342 0 1.616.823 3.043 2.072 3.878-.764-.025-1.482-.234-2.11-.583v.06c0 2.257 1.605 4.14 3.737 4.568-.392.106-.803.162-1.227.162-.3 0-.593-.028-.877-.082.593 1.85 2.313 3.198 4.352 3.234-1.595 1.25-3.604 1.995-5.786 1.995-.376 0-.747-.022-1.112-.065 2.062 1.323 4.51 2.093 7.14 2.093 8.57 0 13.255-7.098 13.255-13.254 0-.2-.005-.402-.014-.602.91-.658 1.7-1.477 2.323-2.41z"/>
-                        </svg>
-                        Twitter
-                    </a>
-                </div>
-
-                <a href="/protected" class="button protected">🔒 Access Protected Area</a>
+                {}
             </div>
         </body>
         </html>
         "#,
-        client_ids.google
-    ))
+        actions,
+        nonce = nonce
+    )))
 }
 
-pub async fn login_page(Extension(client_ids): Extension<ClientIds>) -> Html<String> {
-    Html(format!(
+pub async fn login_page(
+    State(state): State<AppState>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    jar: PrivateCookieJar,
+) -> Result<Html<String>, ApiError> {
+    let enabled = enabled_providers(&state.db).await?;
+    let last_used = last_used_provider(&state, &jar);
+
+    Ok(Html(format!(
         r#"
         <!DOCTYPE html>
         <html>
         <head>
             <title>Login - OAuth Demo</title>
-            <style>
+            <style nonce="{nonce}">
                 body {{
                     font-family: Arial, sans-serif;
                     background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
@@ -171,6 +235,13 @@ pub async fn login_page(Extension(client_ids): Extension<ClientIds>) -> Html<Str
                     transform: translateY(-2px);
                     box-shadow: 0 10px 20px rgba(29, 161, 242, 0.3);
                 }}
+                .icon {{
+                    margin-right: 8px;
+                }}
+                .last-used {{
+                    outline: 3px solid #ffd54f;
+                    outline-offset: 2px;
+                }}
             </style>
         </head>
         <body>
@@ -178,28 +249,89 @@ pub async fn login_page(Extension(client_ids): Extension<ClientIds>) -> Html<Str
                 <h1>Login Required</h1>
                 <p>Please authenticate with one of the following providers:</p>
 
-                <a href="https://accounts.google.com/o/oauth2/v2/auth?scope=openid%20profile%20email&client_id={}&response_type=code&redirect_uri=http://localhost:8000/api/auth/google_callback"
-                   class="oauth-button google-button">
-                    <svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" style="margin-right: 8px;">
-                        <path d="M22.56 12.25c0-.78-.07-1.53-.2-2.25H12v4.26h5.92c-.26 1.37-1.04 2.53-2.21 3.31v2.77h3.57c2.08-1.92 3.28-4.74 3.28-8.09z"/>
-                        <path d="M12 23c2.97 0 5.46-.98 7.28-2.66l-3.57-2.77c-.98.66-2.23 1.06-3.71 1.06-2.86 0-5.29-1.93-6.16-4.53H2.18v2.84C3.99 20.53 7.7 23 12 23z"/>
-                        <path d="M5.84 14.09c-.22-.66-.35-1.36-.35-2.09s.13-1.43.35-2.09V7.07H2.18C1.43 8.55 1 10.22 1 12s.43 3.45 1.18 4.93l2.85-2.22.81-.62z"/>
-                        <path d="M12 5.38c1.62 0 3.06.56 4.21 1.64l3.15-3.15C17.45 2.09 14.97 1 12 1 7.7 1 3.99 3.47 2.18 7.07l3.66 2.84c.87-2.6 3.3-4.53 6.16-4.53z"/>
-                    </svg>
-                    Sign in with Google
-                </a>
-
-                <a href="/api/auth/twitter_login"
-                   class="oauth-button twitter-button">
-                    <svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" style="margin-right: 8px;">
-                        <path d="M23.643 4.937c-.835.37-1.732.62-2.675.733.962-.576 1.7-1.49 2.048-2.578-.9.534-1.897.922-2.958 1.13-.85-.904-2.06-1.47-3.4-1.47-2.572 0-4.658 2.086-4.658 4.66 0 .364.042.718.12 1.06-3.873-.195-7.304-2.05-9.602-4.868-.4.69-.63 1.49-.63 2.342 0 1.616.823 3.043 2.072 3.878-.764-.025-1.482-.234-2.11-.583v.06c0 2.257 1.605 4.14 3.737 4.568-.392.106-.803.162-1.227.162-.3 0-.593-.028-.877-.082.593 1.85 2.313 3.198 4.352 3.234-1.595 1.25-3.604 1.995-5.786 1.995-.376 0-.747-.022-1.112-.065 2.062 1.323 4.51 2.093 7.14 2.093 8.57 0 13.255-7.098 13.255-13.254 0-.2-.005-.402-.014-.602.91-.658 1.7-1.477 2.323-2.41z"/>
-                    </svg>
-                    Sign in with Twitter
-                </a>
+                {}
+                {}
+
+                {}
             </div>
         </body>
         </html>
         "#,
-        client_ids.google
-    ))
+        provider_button(
+            &enabled,
+            last_used,
+            Provider::Google,
+            "oauth-button google-button",
+            "Sign in with Google"
+        ),
+        provider_button(
+            &enabled,
+            last_used,
+            Provider::Twitter,
+            "oauth-button twitter-button",
+            "Sign in with Twitter"
+        ),
+        google_one_tap(&state, &enabled, &oauth_clients),
+        nonce = nonce
+    )))
+}
+
+const GOOGLE_ICON: &str = r#"<svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" class="icon">
+    <path d="M22.56 12.25c0-.78-.07-1.53-.2-2.25H12v4.26h5.92c-.26 1.37-1.04 2.53-2.21 3.31v2.77h3.57c2.08-1.92 3.28-4.74 3.28-8.09z"/>
+    <path d="M12 23c2.97 0 5.46-.98 7.28-2.66l-3.57-2.77c-.98.66-2.23 1.06-3.71 1.06-2.86 0-5.29-1.93-6.16-4.53H2.18v2.84C3.99 20.53 7.7 23 12 23z"/>
+    <path d="M5.84 14.09c-.22-.66-.35-1.36-.35-2.09s.13-1.43.35-2.09V7.07H2.18C1.43 8.55 1 10.22 1 12s.43 3.45 1.18 4.93l2.85-2.22.81-.62z"/>
+    <path d="M12 5.38c1.62 0 3.06.56 4.21 1.64l3.15-3.15C17.45 2.09 14.97 1 12 1 7.7 1 3.99 3.47 2.18 7.07l3.66 2.84c.87-2.6 3.3-4.53 6.16-4.53z"/>
+</svg>"#;
+
+const TWITTER_ICON: &str = r#"<svg width="20" height="20" viewBox="0 0 24 24" fill="currentColor" class="icon">
+    <path d="M23.643 4.937c-.835.37-1.732.62-2.675.733.962-.576 1.7-1.49 2.048-2.578-.9.534-1.897.922-2.958 1.13-.85-.904-2.06-1.47-3.4-1.47-2.572 0-4.658 2.086-4.658 4.66 0 .364.042.718.12 1.06-3.873-.195-7.304-2.05-9.602-4.868-.4.69-.63 1.49-.63 2.342 0 1.616.823 3.043 2.072 3.878-.764-.025-1.482-.234-2.11-.583v.06c0 2.257 1.605 4.14 3.737 4.568-.392.106-.803.162-1.227.162-.3 0-.593-.028-.877-.082.593 1.85 2.313 3.198 4.352 3.234-1.595 1.25-3.604 1.995-5.786 1.995-.376 0-.747-.022-1.112-.065 2.062 1.323 4.51 2.093 7.14 2.093 8.57 0 13.255-7.098 13.255-13.254 0-.2-.005-.402-.014-.602.91-.658 1.7-1.477 2.323-2.41z"/>
+</svg>"#;
+
+fn last_used_provider(state: &AppState, jar: &PrivateCookieJar) -> Option<Provider> {
+    jar.get(&last_provider_cookie_name(&state.settings))
+        .and_then(|cookie| Provider::parse(cookie.value()))
+}
+
+// Login link for a provider, left out while an admin has the provider disabled. The
+// provider used last is highlighted to steer users away from a second account.
+fn provider_button(
+    enabled: &[Provider],
+    last_used: Option<Provider>,
+    provider: Provider,
+    class: &str,
+    label: &str,
+) -> String {
+    if !enabled.contains(&provider) {
+        return String::new();
+    }
+
+    let (class, label) = if last_used == Some(provider) {
+        (
+            format!("{} last-used", class),
+            format!("Continue with {} — last used", provider.label()),
+        )
+    } else {
+        (class.to_string(), label.to_string())
+    };
+
+    let icon = match provider {
+        Provider::Google => GOOGLE_ICON,
+        Provider::Twitter => TWITTER_ICON,
+    };
+
+    format!(
+        r#"<a href="/api/auth/{}_login" class="{}">{}{}</a>"#,
+        provider.as_str(),
+        class,
+        icon,
+        label
+    )
+}
+
+fn google_one_tap(state: &AppState, enabled: &[Provider], oauth_clients: &OAuthClients) -> String {
+    if !enabled.contains(&Provider::Google) {
+        return String::new();
+    }
+
+    one_tap_prompt(&state.settings, oauth_clients.google.client_id().as_str())
 }
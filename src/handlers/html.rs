@@ -0,0 +1,48 @@
+use crate::config::Settings;
+
+// Escape user-controlled text before interpolating it into HTML
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Logout button; logging out is a POST so third-party pages can't trigger it
+pub fn logout_form(csrf_token: &str) -> String {
+    format!(
+        r#"<form method="post" action="/api/auth/logout" class="inline">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <button type="submit" class="button logout">Logout</button>
+                </form>"#,
+        escape_html(csrf_token)
+    )
+}
+
+// Google One Tap prompt; renders nothing unless GOOGLE_ONE_TAP is enabled
+pub fn one_tap_prompt(settings: &Settings, client_id: &str) -> String {
+    if !settings.google_one_tap {
+        return String::new();
+    }
+
+    format!(
+        r#"<script src="https://accounts.google.com/gsi/client" async></script>
+        <div id="g_id_onload"
+             data-client_id="{}"
+             data-login_uri="{}/api/auth/google_one_tap"
+             data-context="signin"
+             data-auto_prompt="true"></div>"#,
+        escape_html(client_id),
+        escape_html(&settings.base_url)
+    )
+}
@@ -0,0 +1,156 @@
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::{Extension, Form};
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::{MaybeUser, UserProfile};
+use crate::middleware::CspNonce;
+use crate::services::legal::{accept_documents, current_documents, pending_documents};
+use crate::state::AppState;
+
+fn document_title(kind: &str) -> &str {
+    match kind {
+        "terms" => "Terms of Service",
+        "privacy" => "Privacy Policy",
+        other => other,
+    }
+}
+
+// Current terms and privacy policy, with an accept form for signed-in users who
+// haven't accepted these versions yet
+pub async fn terms_page(
+    State(state): State<AppState>,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    MaybeUser(user): MaybeUser,
+) -> Result<Html<String>, ApiError> {
+    let documents = current_documents(&state.db).await?;
+
+    let sections = documents
+        .iter()
+        .map(|document| {
+            format!(
+                r#"<section>
+                    <h2>{} <small>version {}</small></h2>
+                    <div class="document">{}</div>
+                </section>"#,
+                document_title(&document.kind),
+                escape_html(&document.version),
+                escape_html(&document.content)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let form = match &user {
+        Some(user) => {
+            let pending = pending_documents(&state.db, user.id).await?;
+            if pending.is_empty() {
+                String::new()
+            } else {
+                let ids = pending
+                    .iter()
+                    .map(|document| document.id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                format!(
+                    r#"<p>Please accept the updated documents to continue.</p>
+                <form method="post" action="/terms">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <input type="hidden" name="document_ids" value="{}">
+                    <button type="submit" class="button">I accept</button>
+                </form>"#,
+                    escape_html(user.csrf_token().unwrap_or_default()),
+                    ids
+                )
+            }
+        }
+        None => String::new(),
+    };
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Terms</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 800px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                .document {{
+                    white-space: pre-wrap;
+                    max-height: 400px;
+                    overflow-y: auto;
+                    background-color: #f8f9fa;
+                    padding: 20px;
+                    border-radius: 5px;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Terms</h1>
+                {}
+                {}
+            </div>
+        </body>
+        </html>
+        "#,
+        sections,
+        form,
+        nonce = nonce
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptTermsForm {
+    pub csrf_token: String,
+    // Comma-separated ids of the document versions that were shown
+    pub document_ids: String,
+}
+
+pub async fn accept_terms(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Form(form): Form<AcceptTermsForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let document_ids = form
+        .document_ids
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ApiError::BadRequest("Invalid document ids".to_string()))?;
+
+    accept_documents(&state.db, user.id, &document_ids).await?;
+
+    // A newer version published in the meantime sends the user straight back here
+    Ok(Redirect::to("/protected"))
+}
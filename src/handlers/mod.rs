@@ -1,11 +1,33 @@
+pub mod activity;
+pub mod admin;
 pub mod auth;
+pub mod conditional;
+pub mod connections;
+pub mod csp_report;
 pub mod extractor;
+pub mod google;
 pub mod health;
 pub mod home;
+pub mod html;
+pub mod legal;
+pub mod onboarding;
+pub mod preferences;
+pub mod support;
+pub mod twitter;
 pub mod user;
 
+pub use activity::*;
+pub use admin::*;
 pub use auth::*;
-pub use extractor::UserProfile;
+pub use connections::*;
+pub use csp_report::*;
+pub use extractor::{CallbackOrigin, ClientIp, DetailedUser, LoginContext, MaybeUser, UserProfile};
+pub use google::*;
 pub use health::*;
 pub use home::*;
+pub use legal::*;
+pub use onboarding::*;
+pub use preferences::*;
+pub use support::*;
+pub use twitter::*;
 pub use user::*;
@@ -0,0 +1,116 @@
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Extension, Form};
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::{DetailedUser, UserProfile};
+use crate::middleware::CspNonce;
+use crate::services::onboarding::{complete_profile, current_step, OnboardingStep};
+use crate::state::AppState;
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 255;
+
+pub async fn onboarding_page(
+    State(state): State<AppState>,
+    DetailedUser(user): DetailedUser,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+) -> Result<Response, ApiError> {
+    if current_step(&state.db, user.id).await? == OnboardingStep::Done {
+        return Ok(Redirect::to("/protected").into_response());
+    }
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Welcome</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 500px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                input[type=text] {{
+                    width: 100%;
+                    box-sizing: border-box;
+                    padding: 10px;
+                    font: inherit;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                    margin-top: 20px;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Welcome!</h1>
+                <p>Complete your profile to get started.</p>
+                <form method="post" action="/onboarding">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <label for="display_name">Display name</label>
+                    <input type="text" id="display_name" name="display_name" value="{}"
+                           maxlength="{}" required>
+                    <button type="submit" class="button">Continue</button>
+                </form>
+            </div>
+        </body>
+        </html>
+        "#,
+        escape_html(user.csrf_token().unwrap_or_default()),
+        escape_html(user.display_name.as_deref().unwrap_or("")),
+        MAX_DISPLAY_NAME_LENGTH,
+        nonce = nonce
+    ))
+    .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileForm {
+    pub csrf_token: String,
+    pub display_name: String,
+}
+
+pub async fn complete_onboarding_profile(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Form(form): Form<ProfileForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let display_name = form.display_name.trim();
+    if display_name.is_empty() || display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Display name must be between 1 and {} characters",
+            MAX_DISPLAY_NAME_LENGTH
+        )));
+    }
+
+    let next = complete_profile(&state.db, user.id, display_name).await?;
+
+    Ok(Redirect::to(match next {
+        OnboardingStep::Done => "/protected",
+        OnboardingStep::Profile => "/onboarding",
+    }))
+}
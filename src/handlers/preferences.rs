@@ -0,0 +1,66 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use oauth2::CsrfToken;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use time::Duration as TimeDuration;
+
+use crate::errors::ApiError;
+use crate::handlers::{LoginContext, MaybeUser};
+use crate::services::cookies::{guest_cookie, guest_cookie_name};
+use crate::services::preferences::{
+    guest_session, list_preferences, set_preference, start_guest_session, PreferenceOwner,
+};
+use crate::state::AppState;
+
+// Start an anonymous session; it becomes part of the account when the guest signs in
+pub async fn start_guest(
+    State(state): State<AppState>,
+    context: LoginContext,
+    jar: PrivateCookieJar,
+    MaybeUser(user): MaybeUser,
+) -> Result<impl IntoResponse, ApiError> {
+    // Signed-in users and existing guests keep what they have
+    if user.is_some() {
+        return Ok((jar, StatusCode::NO_CONTENT));
+    }
+    if let Some(cookie) = jar.get(&guest_cookie_name(&state.settings)) {
+        if guest_session(&state.db, cookie.value()).await?.is_some() {
+            return Ok((jar, StatusCode::NO_CONTENT));
+        }
+    }
+
+    let session_id = CsrfToken::new_random().secret().clone();
+    let days = state.settings.guest_session_days;
+    start_guest_session(&state.db, &context, &session_id, days).await?;
+
+    let cookie = guest_cookie(&state.settings, session_id, TimeDuration::days(days.into()));
+
+    Ok((jar.add(cookie), StatusCode::NO_CONTENT))
+}
+
+pub async fn get_preferences(
+    State(state): State<AppState>,
+    owner: PreferenceOwner,
+) -> Result<Json<BTreeMap<String, String>>, ApiError> {
+    Ok(Json(list_preferences(&state.db, owner).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreferenceValue {
+    pub value: String,
+}
+
+pub async fn put_preference(
+    State(state): State<AppState>,
+    owner: PreferenceOwner,
+    Path(key): Path<String>,
+    Json(body): Json<PreferenceValue>,
+) -> Result<StatusCode, ApiError> {
+    set_preference(&state.db, owner, &key, &body.value).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,333 @@
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::{Extension, Form, Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::{ClientIp, UserProfile};
+use crate::middleware::CspNonce;
+use crate::services::support_access::{
+    active_tokens, create_token, redeem_token, revoke_token, SupportToken, SUPPORT_SCOPES,
+};
+use crate::state::AppState;
+
+// Longest a support token may stay valid
+const MAX_LIFETIME_HOURS: i64 = 72;
+
+fn support_page(
+    nonce: &str,
+    user: &UserProfile,
+    tokens: &[SupportToken],
+    new_token: Option<&str>,
+) -> Html<String> {
+    let created = new_token.map_or(String::new(), |token| {
+        format!(
+            r#"<div class="token">
+                    <p>Give this token to the support agent. It is shown only once.</p>
+                    <code>{}</code>
+                </div>"#,
+            escape_html(token)
+        )
+    });
+
+    let rows = tokens
+        .iter()
+        .map(|token| {
+            format!(
+                r#"<tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>
+                        <form method="post" action="/protected/support-access/{}/revoke">
+                            <input type="hidden" name="csrf_token" value="{}">
+                            <button type="submit" class="button revoke">Revoke</button>
+                        </form>
+                    </td>
+                </tr>"#,
+                escape_html(&token.scopes.join(", ")),
+                token.expires_at.format("%Y-%m-%d %H:%M UTC"),
+                token.id,
+                escape_html(user.csrf_token().unwrap_or_default())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let checkboxes = SUPPORT_SCOPES
+        .iter()
+        .map(|scope| {
+            format!(
+                r#"<label><input type="checkbox" name="{0}" value="on" checked> {0}</label>"#,
+                scope
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Support Access</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 800px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                .token {{
+                    background-color: #fff8e1;
+                    padding: 20px;
+                    border-radius: 5px;
+                    word-break: break-all;
+                }}
+                table {{
+                    width: 100%;
+                    margin: 20px 0;
+                }}
+                label {{
+                    margin-right: 15px;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #4285f4;
+                    color: white;
+                    text-decoration: none;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                    margin: 10px 0;
+                }}
+                .button.revoke {{
+                    background-color: #dc3545;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Support Access</h1>
+                <p>Let a support agent view your account read-only for a limited time,
+                   without sharing your login.</p>
+                {}
+                <form method="post" action="/protected/support-access">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    {}
+                    <p>
+                        <label>Valid for
+                            <input type="number" name="hours" value="24" min="1" max="{}"> hours
+                        </label>
+                    </p>
+                    <button type="submit" class="button">Create token</button>
+                </form>
+                <h2>Active tokens</h2>
+                <table>
+                    <tr><th>Access</th><th>Expires</th><th></th></tr>
+                    {}
+                </table>
+                <a href="/protected" class="button">Back</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        created,
+        escape_html(user.csrf_token().unwrap_or_default()),
+        checkboxes,
+        MAX_LIFETIME_HOURS,
+        rows,
+        nonce = nonce
+    ))
+}
+
+pub async fn support_access_page(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+) -> Result<Html<String>, ApiError> {
+    let tokens = active_tokens(&state.db, user.id).await?;
+
+    Ok(support_page(&nonce, &user, &tokens, None))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SupportTokenForm {
+    pub csrf_token: String,
+    pub hours: i64,
+    // Checkboxes are only submitted when ticked
+    pub profile: Option<String>,
+    pub identities: Option<String>,
+    pub sessions: Option<String>,
+}
+
+pub async fn create_support_token(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    Form(form): Form<SupportTokenForm>,
+) -> Result<Html<String>, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let scopes: Vec<String> = [
+        ("profile", &form.profile),
+        ("identities", &form.identities),
+        ("sessions", &form.sessions),
+    ]
+    .into_iter()
+    .filter(|(_, checked)| checked.is_some())
+    .map(|(scope, _)| scope.to_string())
+    .collect();
+
+    if scopes.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Select at least one kind of access".to_string(),
+        ));
+    }
+
+    let lifetime = Duration::hours(form.hours.clamp(1, MAX_LIFETIME_HOURS));
+    let token = create_token(&state.db, user.id, &scopes, lifetime).await?;
+    let tokens = active_tokens(&state.db, user.id).await?;
+
+    Ok(support_page(&nonce, &user, &tokens, Some(&token)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeForm {
+    pub csrf_token: String,
+}
+
+pub async fn revoke_support_token(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Path(id): Path<i32>,
+    Form(form): Form<RevokeForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    revoke_token(&state.db, user.id, id).await?;
+
+    Ok(Redirect::to("/protected/support-access"))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SupportProfile {
+    pub email: String,
+    pub display_name: Option<String>,
+    pub roles: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SupportIdentity {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SupportSession {
+    pub created_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupportView {
+    pub user_id: i32,
+    pub profile: Option<SupportProfile>,
+    pub identities: Option<Vec<SupportIdentity>>,
+    pub sessions: Option<Vec<SupportSession>>,
+}
+
+// Read-only account view for support agents. The agent must be signed in with the
+// support (or admin) role and present the user's token in X-Support-Token.
+pub async fn support_account(
+    State(state): State<AppState>,
+    agent: UserProfile,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+) -> Result<Json<SupportView>, ApiError> {
+    if !agent.has_role("support") && !agent.has_role("admin") {
+        return Err(ApiError::Forbidden);
+    }
+
+    let token = headers
+        .get("x-support-token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(ApiError::Unauthorized)?;
+
+    let grant = redeem_token(&state.db, token, agent.id, ip)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    let profile = if grant.allows("profile") {
+        sqlx::query_as(
+            "SELECT email, display_name, roles, created_at, last_login_at
+             FROM users WHERE id = $1",
+        )
+        .bind(grant.user_id)
+        .fetch_optional(&state.db)
+        .await?
+    } else {
+        None
+    };
+
+    let identities = if grant.allows("identities") {
+        Some(
+            sqlx::query_as(
+                "SELECT provider, provider_user_id, created_at, last_login_at
+                 FROM user_identities WHERE user_id = $1
+                 ORDER BY created_at",
+            )
+            .bind(grant.user_id)
+            .fetch_all(&state.db)
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let sessions = if grant.allows("sessions") {
+        Some(
+            sqlx::query_as(
+                "SELECT created_at, expires_at, ip_address, user_agent, country, city
+                 FROM sessions WHERE user_id = $1 AND expires_at > NOW()
+                 ORDER BY created_at DESC",
+            )
+            .bind(grant.user_id)
+            .fetch_all(&state.db)
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Json(SupportView {
+        user_id: grant.user_id,
+        profile,
+        identities,
+        sessions,
+    }))
+}
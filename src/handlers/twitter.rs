@@ -0,0 +1,162 @@
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::{Extension, Form};
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::handlers::html::escape_html;
+use crate::handlers::UserProfile;
+use crate::middleware::CspNonce;
+use crate::oauth::{OAuthClients, Provider};
+use crate::services::provider_tokens::IdentityToken;
+use crate::services::twitter_api::{post_tweet, recent_tweets};
+use crate::state::AppState;
+
+// Tweets are limited to 280 characters
+const MAX_TWEET_LENGTH: usize = 280;
+
+async fn twitter_access_token(
+    state: &AppState,
+    oauth_clients: &OAuthClients,
+    user: &UserProfile,
+) -> Result<(String, IdentityToken), ApiError> {
+    let mut identity = IdentityToken::load(&state.db, user.id, Provider::Twitter)
+        .await?
+        .ok_or_else(|| {
+            ApiError::BadRequest("Sign in with Twitter to use the Twitter API".to_string())
+        })?;
+
+    let access_token = identity
+        .access_token(&state.db, &oauth_clients.twitter)
+        .await?;
+
+    Ok((access_token, identity))
+}
+
+// Example use of the stored Twitter token: the user's recent tweets and a post form
+pub async fn tweets_page(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+    Extension(oauth_clients): Extension<OAuthClients>,
+) -> Result<Html<String>, ApiError> {
+    let (access_token, identity) = twitter_access_token(&state, &oauth_clients, &user).await?;
+    let tweets = recent_tweets(&state.ctx, &access_token, &identity.provider_user_id).await?;
+
+    let items = if tweets.is_empty() {
+        "<p>No tweets yet.</p>".to_string()
+    } else {
+        tweets
+            .iter()
+            .map(|tweet| {
+                format!(
+                    r#"<div class="tweet">
+                    <p>{}</p>
+                    <small><a href="https://twitter.com/i/web/status/{}">{}</a></small>
+                </div>"#,
+                    escape_html(&tweet.text),
+                    escape_html(&tweet.id),
+                    escape_html(tweet.created_at.as_deref().unwrap_or("View"))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(Html(format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Your Tweets</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 800px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                }}
+                .tweet {{
+                    background-color: #f0f8ff;
+                    padding: 10px 20px;
+                    border-radius: 5px;
+                    margin: 10px 0;
+                }}
+                textarea {{
+                    width: 100%;
+                    box-sizing: border-box;
+                    font: inherit;
+                }}
+                .button {{
+                    display: inline-block;
+                    padding: 10px 20px;
+                    background-color: #1DA1F2;
+                    color: white;
+                    text-decoration: none;
+                    border: none;
+                    border-radius: 5px;
+                    font: inherit;
+                    cursor: pointer;
+                    margin: 10px 0;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Your Tweets</h1>
+                <form method="post" action="/protected/tweets">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <textarea name="text" rows="3" maxlength="{}" required></textarea>
+                    <button type="submit" class="button">Tweet</button>
+                </form>
+                {}
+                <a href="/protected" class="button">Back</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        escape_html(user.csrf_token().unwrap_or_default()),
+        MAX_TWEET_LENGTH,
+        items,
+        nonce = nonce
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TweetForm {
+    pub csrf_token: String,
+    pub text: String,
+}
+
+// Posting needs the tweet.write scope in TWITTER_SCOPES
+pub async fn create_tweet(
+    State(state): State<AppState>,
+    user: UserProfile,
+    Extension(oauth_clients): Extension<OAuthClients>,
+    Form(form): Form<TweetForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    if user.csrf_token() != Some(form.csrf_token.as_str()) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let text = form.text.trim();
+    if text.is_empty() || text.chars().count() > MAX_TWEET_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Tweets must be between 1 and {} characters",
+            MAX_TWEET_LENGTH
+        )));
+    }
+
+    let (access_token, _) = twitter_access_token(&state, &oauth_clients, &user).await?;
+    post_tweet(&state.ctx, &access_token, text).await?;
+
+    Ok(Redirect::to("/protected/tweets"))
+}
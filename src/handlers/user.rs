@@ -1,13 +1,18 @@
-use axum::response::{Html, IntoResponse};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Extension;
 
-use crate::handlers::UserProfile;
+use crate::handlers::conditional::conditional_json;
+use crate::handlers::html::{escape_html, logout_form};
+use crate::handlers::DetailedUser;
+use crate::middleware::CspNonce;
+use crate::oauth::Provider;
 
-pub async fn protected(user: UserProfile) -> Html<String> {
-    let provider = if user.email.ends_with("@twitter.local") {
-        "Twitter"
-    } else {
-        "Google"
-    };
+pub async fn protected(
+    DetailedUser(user): DetailedUser,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+) -> Html<String> {
+    let provider = user.provider().map_or("Unknown", |p| p.label());
 
     Html(format!(
         r#"
@@ -15,7 +20,7 @@ pub async fn protected(user: UserProfile) -> Html<String> {
         <html>
         <head>
             <title>Protected Area</title>
-            <style>
+            <style nonce="{nonce}">
                 body {{
                     font-family: Arial, sans-serif;
                     background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
@@ -47,6 +52,12 @@ pub async fn protected(user: UserProfile) -> Html<String> {
                 }}
                 .button.logout {{
                     background-color: #dc3545;
+                    border: none;
+                    font: inherit;
+                    cursor: pointer;
+                }}
+                .inline {{
+                    display: inline;
                 }}
             </style>
         </head>
@@ -59,29 +70,51 @@ pub async fn protected(user: UserProfile) -> Html<String> {
                     <p>Provider: <strong>{}</strong></p>
                 </div>
                 <a href="/protected/profile" class="button">View Profile</a>
-                <a href="/api/auth/logout" class="button logout">Logout</a>
+                <a href="/protected/activity" class="button">Account Activity</a>
+                <a href="/protected/connections" class="button">Connected Accounts</a>
+                {}
             </div>
         </body>
         </html>
         "#,
-        user.email, provider
+        escape_html(&user.email),
+        provider,
+        logout_form(user.csrf_token().unwrap_or_default()),
+        nonce = nonce
     ))
 }
 
-pub async fn get_profile(user: UserProfile) -> impl IntoResponse {
-    let (provider, display_name) = if user.email.ends_with("@twitter.local") {
-        ("Twitter", user.email.replace("@twitter.local", ""))
-    } else {
-        ("Google", user.email.clone())
+pub async fn get_profile(
+    DetailedUser(user): DetailedUser,
+    Extension(CspNonce(nonce)): Extension<CspNonce>,
+) -> impl IntoResponse {
+    let provider = user.provider().map_or("Unknown", |p| p.label());
+
+    let last_login = match (user.last_login_at, user.provider()) {
+        (Some(at), Some(via)) => {
+            format!("{} via {}", at.format("%Y-%m-%d %H:%M UTC"), via.label())
+        }
+        (Some(at), None) => at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        _ => "Never".to_string(),
     };
 
+    // Providers linked to this account
+    let linked = user
+        .identities
+        .iter()
+        .map(|identity| {
+            Provider::parse(&identity.provider).map_or(identity.provider.as_str(), |p| p.label())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
     Html(format!(
         r#"
         <!DOCTYPE html>
         <html>
         <head>
             <title>User Profile</title>
-            <style>
+            <style nonce="{nonce}">
                 body {{
                     font-family: Arial, sans-serif;
                     background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
@@ -113,11 +146,23 @@ pub async fn get_profile(user: UserProfile) -> impl IntoResponse {
                 <p><strong>Provider:</strong> {}</p>
                 <p><strong>Display Name:</strong> {}</p>
                 <p><strong>Email/ID:</strong> {}</p>
+                <p><strong>Last Login:</strong> {}</p>
+                <p><strong>Linked Accounts:</strong> {}</p>
                 <a href="/protected" class="button">Back to Protected Area</a>
             </div>
         </body>
         </html>
         "#,
-        provider, display_name, user.email
+        provider,
+        escape_html(user.name()),
+        escape_html(&user.email),
+        last_login,
+        linked,
+        nonce = nonce
     ))
 }
+
+// The signed-in user as JSON, for API clients and SPAs polling the session
+pub async fn me(DetailedUser(user): DetailedUser, headers: HeaderMap) -> Response {
+    conditional_json(&headers, &user)
+}
@@ -0,0 +1,11 @@
+// The app as a library, so benches and embedders can reach the router, the
+// middleware and the services; main.rs wires it up as the server binary
+pub mod config;
+pub mod errors;
+pub mod grpc;
+pub mod handlers;
+pub mod middleware;
+pub mod oauth;
+pub mod server;
+pub mod services;
+pub mod state;
@@ -1,59 +1,97 @@
 use anyhow::Result;
+use log::LevelFilter;
 use oauth2::basic::BasicClient;
 use reqwest::Client as ReqwestClient;
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::ConnectOptions;
 use std::collections::HashMap;
 use std::env;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
 use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
 
-mod config;
-use config::init_router;
+use oauth_axum::config::reload::{
+    log_filter, parse_cors_origins, spawn_log_level_switcher, spawn_sighup_reloader, LiveConfig,
+};
+use oauth_axum::config::settings::SessionMode;
+use oauth_axum::config::{cookie_key, init_router, Settings};
+use oauth_axum::oauth::{GoogleKeys, OAuthClients, PkceVerifiers, Provider};
+use oauth_axum::services::alerts::AlertKind;
+use oauth_axum::services::pool_metrics::PoolMetrics;
+use oauth_axum::services::post_login::PostLoginHooks;
+use oauth_axum::services::{
+    Alerts, GeoIp, LoginThrottle, Maintenance, RateLimiter, RevokedSessions,
+};
+use oauth_axum::state::AppState;
+use oauth_axum::{grpc, server, services};
 
-mod errors;
-
-mod handlers;
-
-mod middleware;
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenv::dotenv().ok();
 
-mod oauth;
-use oauth::{ClientIds, OAuthClients, PkceVerifiers};
+    let settings = Arc::new(Settings::from_env());
 
-mod services;
+    // Error reporting; panics are captured from here on. The guard flushes pending
+    // events when main returns.
+    let _sentry = settings.sentry_dsn.as_deref().map(|dsn| {
+        sentry::init((
+            dsn,
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                environment: settings.sentry_environment.clone().map(Into::into),
+                ..Default::default()
+            },
+        ))
+    });
 
-mod state;
-use state::AppState;
+    // Initialize tracing; span timings are logged when each span closes
+    let span_events = if settings.log_span_timings {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize tracing
+    // The filter sits behind a reload layer so SIGHUP can change it
+    let (filter_layer, log_filter_handle) = tracing_subscriber::reload::Layer::new(log_filter());
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "oauth_axum=debug,axum::rejection=trace".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().with_span_events(span_events))
         .init();
 
-    // Load environment variables
-    dotenv::dotenv().ok();
-
-    // Database connection
+    // Database connection. Statements slower than the threshold are logged with their
+    // SQL text; bound parameters are never included.
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let connect_options = PgConnectOptions::from_str(&database_url)
+        .expect("Invalid DATABASE_URL")
+        .log_statements(LevelFilter::Trace)
+        .log_slow_statements(
+            LevelFilter::Warn,
+            StdDuration::from_millis(settings.slow_query_threshold_ms),
+        )
+        // A stuck query is cancelled by the server instead of holding its connection
+        .options([(
+            "statement_timeout",
+            settings.database_statement_timeout_ms.to_string(),
+        )]);
     let db = PgPoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(StdDuration::from_secs(3))
-        .connect(&database_url)
+        .max_connections(settings.database_max_connections)
+        .acquire_timeout(StdDuration::from_millis(
+            settings.database_acquire_timeout_ms,
+        ))
+        .connect_with(connect_options)
         .await
         .expect("Failed to connect to database");
 
-    // Run migrations
-    sqlx::migrate!("./migrations")
-        .run(&db)
-        .await
-        .expect("Failed to run migrations");
+    // Run migrations, one replica at a time
+    services::schema::run_migrations(
+        &db,
+        StdDuration::from_secs(settings.migration_lock_timeout_secs),
+    )
+    .await
+    .expect("Failed to run migrations");
 
     // Create HTTP client with timeout
     let ctx = ReqwestClient::builder()
@@ -72,63 +110,182 @@ async fn main() -> Result<()> {
         env::var("TWITTER_OAUTH_CLIENT_SECRET").expect("TWITTER_OAUTH_CLIENT_SECRET not set");
 
     let google_client = BasicClient::new(
-        oauth2::ClientId::new(google_client_id.clone()),
+        oauth2::ClientId::new(google_client_id),
         Some(oauth2::ClientSecret::new(google_client_secret)),
         oauth2::AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string())?,
         Some(oauth2::TokenUrl::new(
             "https://oauth2.googleapis.com/token".to_string(),
         )?),
     )
-    .set_redirect_uri(oauth2::RedirectUrl::new(
-        "http://localhost:8000/api/auth/google_callback".to_string(),
+    // Replaced per request when the Host header is another callback origin
+    .set_redirect_uri(Provider::Google.redirect_url(&settings.callback_origins[0]))
+    .set_revocation_uri(oauth2::RevocationUrl::new(
+        "https://oauth2.googleapis.com/revoke".to_string(),
     )?);
 
     let twitter_client = BasicClient::new(
-        oauth2::ClientId::new(twitter_client_id.clone()),
+        oauth2::ClientId::new(twitter_client_id),
         Some(oauth2::ClientSecret::new(twitter_client_secret)),
         oauth2::AuthUrl::new("https://twitter.com/i/oauth2/authorize".to_string())?,
         Some(oauth2::TokenUrl::new(
             "https://api.twitter.com/2/oauth2/token".to_string(),
         )?),
     )
-    .set_redirect_uri(oauth2::RedirectUrl::new(
-        "http://localhost:8000/api/auth/twitter_callback".to_string(),
+    .set_redirect_uri(Provider::Twitter.redirect_url(&settings.callback_origins[0]))
+    .set_revocation_uri(oauth2::RevocationUrl::new(
+        "https://api.twitter.com/2/oauth2/revoke".to_string(),
     )?);
 
-    // Generate a secure key for cookie encryption
-    let cookie_key = env::var("COOKIE_KEY").unwrap_or_else(|_| {
-        "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".to_string()
-    });
+    // Key for cookie encryption
+    let key = cookie_key();
 
-    let key = axum_extra::extract::cookie::Key::from(cookie_key.as_bytes());
+    // Periodically remove expired sessions
+    services::session::spawn_session_pruner(
+        db.clone(),
+        StdDuration::from_secs(settings.session_prune_interval_secs),
+        settings.session_prune_batch_size,
+    );
+
+    // Permanently remove soft-deleted users after the retention period
+    services::retention::spawn_user_purger(
+        db.clone(),
+        StdDuration::from_secs(settings.user_purge_interval_secs),
+        settings.user_retention_days,
+    );
+
+    // Security alerts are delivered to the configured webhooks
+    let alerts = Alerts::new(ctx.clone(), &settings.alert_webhooks);
+    if alerts.wants(AlertKind::AdminGranted) {
+        services::alerts::spawn_admin_grant_watcher(db.clone(), alerts.clone());
+    }
+
+    // Keep monthly auth event partitions ahead of time and drop expired ones
+    services::event_partitions::spawn_partition_maintainer(
+        db.clone(),
+        settings.audit_retention_months,
+    );
+
+    let throttle = LoginThrottle::new(
+        alerts.clone(),
+        settings.login_throttle_free_attempts,
+        StdDuration::from_secs(settings.login_throttle_base_delay_secs),
+        StdDuration::from_secs(settings.login_throttle_max_delay_secs),
+    );
+
+    // Optional GeoIP database for login locations
+    let geoip = settings
+        .geoip_database
+        .as_deref()
+        .map(|path| Arc::new(GeoIp::open(path).expect("Failed to open GeoIP database")));
+
+    // Sample how long getting a pooled connection takes, for /metrics
+    let pool_metrics = PoolMetrics::default();
+    services::pool_metrics::spawn_pool_sampler(db.clone(), pool_metrics.clone());
 
     // Build app state
-    let state = AppState { db, ctx, key };
+    let maintenance = Maintenance::new(settings.maintenance_mode);
+    let csp_report_limiter = RateLimiter::new(
+        settings.csp_report_limit_per_minute,
+        StdDuration::from_secs(60),
+    );
+
+    let cors_origins =
+        parse_cors_origins(&settings.cors_allowed_origins).unwrap_or_else(|e| panic!("{}", e));
+    let live_config = LiveConfig::new(cors_origins, log_filter_handle);
+
+    let state = AppState {
+        db,
+        ctx,
+        key,
+        settings,
+        throttle,
+        geoip,
+        google_keys: GoogleKeys::new(),
+        maintenance,
+        csp_report_limiter,
+        live_config,
+        alerts,
+        revoked_sessions: RevokedSessions::default(),
+        pool_metrics,
+        post_login_hooks: PostLoginHooks::default(),
+    };
+
+    // Session revocations and the maintenance flag are shared with other instances
+    // through Postgres notifications
+    services::invalidation::spawn_invalidation_listener(
+        state.db.clone(),
+        state.maintenance.clone(),
+        (state.settings.session_mode == SessionMode::Stateless)
+            .then(|| state.revoked_sessions.clone()),
+    );
 
     let oauth_clients = OAuthClients {
         google: google_client,
         twitter: twitter_client,
     };
 
-    let client_ids = ClientIds {
-        google: google_client_id,
-        twitter: twitter_client_id,
-    };
+    // Refresh provider tokens of active users before they expire
+    services::provider_tokens::spawn_token_refresher(
+        state.db.clone(),
+        oauth_clients.clone(),
+        StdDuration::from_secs(state.settings.token_refresh_interval_secs),
+        StdDuration::from_secs(state.settings.token_refresh_window_secs),
+    );
+
+    // Reapply the reloadable settings from .env on SIGHUP
+    spawn_sighup_reloader(state.clone());
+    spawn_log_level_switcher(state.live_config.clone());
+
+    // Forward auth events to a syslog server, file or collector for the SIEM
+    if let Some(sink) = state.settings.audit_export.clone() {
+        services::audit_export::spawn_audit_exporter(
+            state.db.clone(),
+            state.ctx.clone(),
+            sink,
+            state.settings.audit_export_format,
+            StdDuration::from_secs(state.settings.audit_export_interval_secs),
+        );
+    }
 
     let pkce_verifiers: PkceVerifiers = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
 
     // Build router
-    let app = init_router(state.clone(), oauth_clients, client_ids, pkce_verifiers);
+    let app = init_router(state.clone(), oauth_clients, pkce_verifiers, Vec::new());
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-
-    info!("Server running on http://localhost:8000");
+    let listeners = server::bind(&state.settings.listen, &state.settings.trusted_proxies).await?;
+    for listener in &listeners {
+        info!("Server listening on {}", listener.describe());
+    }
     info!("OAuth endpoints:");
-    info!("  - Google: http://localhost:8000/api/auth/google_callback");
-    info!("  - Twitter: http://localhost:8000/api/auth/twitter_callback");
+    for origin in &state.settings.callback_origins {
+        info!(
+            "  - Google: {}",
+            Provider::Google.redirect_url(origin).as_str()
+        );
+        info!(
+            "  - Twitter: {}",
+            Provider::Twitter.redirect_url(origin).as_str()
+        );
+    }
+
+    // Session checks for sibling backends, on their own port
+    if let Some(addr) = state.settings.grpc_listen {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("gRPC session service listening on {}", addr);
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = grpc::serve(state, listener).await {
+                tracing::error!("gRPC session service stopped: {}", e);
+            }
+        });
+    }
+
+    // Migrations have run and the providers are configured
+    server::notify_ready();
 
-    axum::serve(listener, app).await.unwrap();
+    server::serve(app, listeners).await?;
 
     Ok(())
 }
@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware,
+    response::Response,
+};
+use oauth2::CsrfToken;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use crate::config::settings::AccessLogFormat;
+use crate::state::AppState;
+
+const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// Filled in by the user extractor once a request is authenticated
+#[derive(Clone, Default)]
+pub struct RequestUser(Arc<OnceLock<i32>>);
+
+impl RequestUser {
+    pub fn set(&self, user_id: i32) {
+        let _ = self.0.set(user_id);
+
+        // Errors reported from here on are attributed to the user
+        sentry::configure_scope(|scope| {
+            scope.set_user(Some(sentry::User {
+                id: Some(user_id.to_string()),
+                ..Default::default()
+            }))
+        });
+    }
+
+    fn get(&self) -> Option<i32> {
+        self.0.get().copied()
+    }
+}
+
+// Id of the request, as sent back in X-Request-Id
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+// Log one line per request. Only the path is logged, since query strings can carry
+// authorization codes and state values.
+pub async fn access_log(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: middleware::Next,
+) -> Response {
+    let settings = &state.settings;
+    let path = req.uri().path().to_string();
+
+    // Keep the id assigned by a proxy in front of us, otherwise make one up
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| CsrfToken::new_random().secret().clone());
+
+    let user = RequestUser::default();
+    req.extensions_mut().insert(user.clone());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    if settings.access_log_format == AccessLogFormat::Off
+        || settings
+            .access_log_exclude
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(req).await;
+    }
+
+    let method = req.method().clone();
+    let started = Instant::now();
+
+    let mut response = next.run(req).await;
+
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let status = response.status().as_u16();
+    let user_id = user.get();
+
+    match settings.access_log_format {
+        AccessLogFormat::Text => tracing::info!(
+            "{} {} {} {:.1}ms user={} request_id={}",
+            method,
+            path,
+            status,
+            latency_ms,
+            user_id.map_or("-".to_string(), |id| id.to_string()),
+            request_id
+        ),
+        AccessLogFormat::Json => tracing::info!(
+            "{}",
+            serde_json::json!({
+                "method": method.as_str(),
+                "path": path,
+                "status": status,
+                "latency_ms": latency_ms,
+                "user_id": user_id,
+                "request_id": request_id,
+            })
+        ),
+        AccessLogFormat::Off => {}
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID, value);
+    }
+
+    response
+}
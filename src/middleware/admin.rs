@@ -0,0 +1,83 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware,
+    response::Response,
+};
+use std::net::SocketAddr;
+
+use crate::config::settings::normalize_fingerprint;
+use crate::errors::ApiError;
+use crate::handlers::{ClientIp, UserProfile};
+use crate::state::AppState;
+
+// Only allow requests from the configured admin networks
+pub async fn restrict_admin_ips(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    let allowed = &state.settings.admin_allowed_networks;
+
+    if !allowed.is_empty() && !allowed.iter().any(|net| net.contains(&ip)) {
+        tracing::warn!("Rejected admin request from {}", ip);
+        return Err(ApiError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
+
+// Mutual TLS is terminated by the reverse proxy, which reports the verified client
+// certificate in headers. Those headers are only believed when the request comes
+// straight from a trusted proxy, otherwise any client could set them.
+pub async fn require_client_cert(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    let settings = &state.settings;
+
+    if !settings.admin_require_client_cert {
+        return Ok(next.run(req).await);
+    }
+
+    let from_proxy = settings
+        .trusted_proxies
+        .iter()
+        .any(|net| net.contains(&peer.ip()));
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    let verified = from_proxy && header(&settings.client_cert_verify_header) == Some("SUCCESS");
+    let fingerprint = header(&settings.client_cert_fingerprint_header).map(normalize_fingerprint);
+
+    let allowed = &settings.admin_client_cert_fingerprints;
+    let fingerprint_allowed =
+        allowed.is_empty() || fingerprint.is_some_and(|fingerprint| allowed.contains(&fingerprint));
+
+    if !verified || !fingerprint_allowed {
+        tracing::warn!(
+            "Rejected admin request without a valid client certificate from {}",
+            peer
+        );
+        return Err(ApiError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
+
+pub async fn require_admin(
+    user: UserProfile,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    if !user.has_role("admin") {
+        return Err(ApiError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
@@ -4,45 +4,100 @@ use axum::{
     middleware,
     response::{IntoResponse, Redirect, Response},
 };
-use axum_extra::extract::cookie::{Cookie, PrivateCookieJar};
-use time::Duration as TimeDuration;
+use axum_extra::extract::cookie::PrivateCookieJar;
 
+use crate::config::settings::SessionMode;
+use crate::errors::ApiError;
+use crate::handlers::LoginContext;
+use crate::services::cookies::session_removal_cookie;
+use crate::services::fingerprint::session_fingerprint;
+use crate::services::stateless_session::{self, SessionClaims};
 use crate::state::AppState;
 
+// Verify session exists, hasn't expired and matches the client it is bound to
+#[tracing::instrument(level = "debug", skip_all)]
+async fn session_is_live(
+    state: &AppState,
+    session_id: &str,
+    fingerprint: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let session: Option<(i32,)> = sqlx::query_as(
+        "SELECT sessions.user_id FROM sessions
+         JOIN users ON sessions.user_id = users.id
+         WHERE sessions.session_id = $1 AND sessions.expires_at > NOW()
+           AND users.deleted_at IS NULL
+           AND ($2::text IS NULL OR sessions.fingerprint = $2)",
+    )
+    .bind(session_id)
+    .bind(fingerprint)
+    .fetch_optional(&state.db)
+    .await?;
+
+    Ok(session.is_some())
+}
+
 pub async fn check_authenticated(
     State(state): State<AppState>,
     jar: PrivateCookieJar,
+    context: LoginContext,
     mut req: Request,
     next: middleware::Next,
 ) -> Result<Response, StatusCode> {
-    let Some(cookie) = jar.get("sid").map(|c| c.value().to_owned()) else {
+    let Some(cookie) = jar
+        .get(&state.settings.session_cookie_name)
+        .map(|c| c.value().to_owned())
+    else {
         return Ok(Redirect::to("/login").into_response());
     };
 
-    // Verify session exists and hasn't expired
-    let result: Result<(i64,), _> = sqlx::query_as(
-        "SELECT COUNT(*) as count FROM sessions
-         WHERE session_id = $1 AND expires_at > NOW()",
-    )
-    .bind(&cookie)
-    .fetch_one(&state.db)
-    .await;
+    let fingerprint = session_fingerprint(&state.settings, &context);
+
+    // Stateless sessions are validated from the cookie alone
+    let result = match state.settings.session_mode {
+        SessionMode::Database => session_is_live(&state, &cookie, fingerprint.as_deref()).await,
+        SessionMode::Stateless => Ok(SessionClaims::decode(&cookie).is_some_and(|claims| {
+            claims.matches(fingerprint.as_deref())
+                && !state.revoked_sessions.is_revoked(&claims.sid)
+        })),
+    };
 
     match result {
-        Ok((count,)) if count > 0 => {
+        Ok(true) => {
             req.extensions_mut().insert(cookie);
             Ok(next.run(req).await)
         }
         _ => {
             // Invalid or expired session - remove the cookie and redirect
-            let removal_cookie = Cookie::build(("sid", ""))
-                .path("/")
-                .http_only(true)
-                .same_site(axum_extra::extract::cookie::SameSite::Lax)
-                .max_age(TimeDuration::seconds(-1));
+            let removal_cookie = session_removal_cookie(&state.settings);
 
             let jar = jar.add(removal_cookie);
             Ok((jar, Redirect::to("/login")).into_response())
         }
     }
 }
+
+// Guard for high-risk routes: in stateless mode the session must still exist in the
+// database, so logouts, deletions and newer logins revoke it here immediately.
+// Database-mode sessions are already checked on every request.
+pub async fn require_live_session(
+    State(state): State<AppState>,
+    jar: PrivateCookieJar,
+    context: LoginContext,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    if state.settings.session_mode == SessionMode::Stateless {
+        let session_id = jar
+            .get(&state.settings.session_cookie_name)
+            .and_then(|cookie| stateless_session::session_id(&state.settings, cookie.value()))
+            .ok_or(ApiError::Unauthorized)?;
+
+        let fingerprint = session_fingerprint(&state.settings, &context);
+
+        if !session_is_live(&state, &session_id, fingerprint.as_deref()).await? {
+            return Err(ApiError::Unauthorized);
+        }
+    }
+
+    Ok(next.run(req).await)
+}
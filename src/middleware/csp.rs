@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware,
+    response::Response,
+};
+use oauth2::CsrfToken;
+
+use crate::state::AppState;
+
+// Per-request nonce that templates put on their inline <style> and <script> tags
+#[derive(Clone)]
+pub struct CspNonce(pub String);
+
+pub async fn content_security_policy(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: middleware::Next,
+) -> Response {
+    // CsrfToken doubles as a source of random URL-safe base64
+    let nonce = CsrfToken::new_random().secret().clone();
+    req.extensions_mut().insert(CspNonce(nonce.clone()));
+
+    let mut response = next.run(req).await;
+
+    // One Tap loads its script, stylesheet and iframe from accounts.google.com
+    let (script_src, style_src, google) = if state.settings.google_one_tap {
+        (
+            " https://accounts.google.com/gsi/client",
+            " https://accounts.google.com/gsi/style",
+            "frame-src https://accounts.google.com/gsi/; \
+             connect-src 'self' https://accounts.google.com/gsi/; ",
+        )
+    } else {
+        ("", "", "")
+    };
+
+    let policy = format!(
+        "default-src 'self'; script-src 'self' 'nonce-{0}'{1}; style-src 'self' 'nonce-{0}'{2}; \
+         img-src 'self' data:; object-src 'none'; base-uri 'none'; {3}\
+         frame-ancestors 'self'; form-action 'self'; \
+         report-uri /csp-report; report-to csp-endpoint",
+        nonce, script_src, style_src, google
+    );
+
+    if let Ok(value) = HeaderValue::from_str(&policy) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_SECURITY_POLICY, value);
+    }
+
+    // report-uri is the legacy mechanism; newer browsers use the named endpoint
+    response.headers_mut().insert(
+        "reporting-endpoints",
+        HeaderValue::from_static("csp-endpoint=\"/csp-report\""),
+    );
+
+    response
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::HeaderValue,
+    middleware,
+    response::Response,
+};
+
+use crate::state::AppState;
+
+// When the unversioned /api routes were deprecated in favour of /api/v1
+// (2026-01-01T00:00:00Z), as a Unix timestamp
+const LEGACY_API_DEPRECATED_AT: i64 = 1_767_225_600;
+
+// Responses of the unversioned /api alias announce the deprecation (RFC 9745), the
+// optional sunset date (RFC 8594) and the versioned successor of the same route
+pub async fn deprecated_api(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    req: Request,
+    next: middleware::Next,
+) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+
+    headers.insert(
+        "deprecation",
+        HeaderValue::from_str(&format!("@{}", LEGACY_API_DEPRECATED_AT))
+            .expect("valid header value"),
+    );
+
+    if let Some(sunset) = state.settings.legacy_api_sunset {
+        let date = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert("sunset", value);
+        }
+    }
+
+    let successor = uri.path().replacen("/api/", "/api/v1/", 1);
+    if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+    {
+        headers.insert("link", value);
+    }
+
+    response
+}
@@ -0,0 +1,34 @@
+use axum::{extract::Request, middleware, response::Response};
+use sentry::{Hub, Level, SentryFutureExt};
+use std::sync::Arc;
+
+use crate::errors::ServerError;
+use crate::middleware::RequestId;
+
+// Send server-side failures to Sentry with the request's path and id. Each request
+// gets its own hub, so the user set by the extractor and panics raised while the
+// handler runs are attributed to this request.
+pub async fn report_errors(req: Request, next: middleware::Next) -> Response {
+    // SENTRY_DSN is not set
+    if Hub::current().client().is_none() {
+        return next.run(req).await;
+    }
+
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    hub.configure_scope(|scope| {
+        // Only the path; query strings can carry authorization codes
+        scope.set_tag("route", req.uri().path());
+        scope.set_tag("method", req.method());
+        if let Some(RequestId(id)) = req.extensions().get::<RequestId>() {
+            scope.set_tag("request_id", id);
+        }
+    });
+
+    let response = next.run(req).bind_hub(hub.clone()).await;
+
+    if let Some(ServerError(detail)) = response.extensions().get::<ServerError>() {
+        hub.capture_message(detail, Level::Error);
+    }
+
+    response
+}
@@ -0,0 +1,101 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, StatusCode},
+    middleware,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::handlers::MaybeUser;
+use crate::middleware::CspNonce;
+use crate::state::AppState;
+
+// Still reachable during maintenance: health checks, the admin API and the login flow,
+// so admins can sign in and switch maintenance off again
+const EXEMPT_PREFIXES: [&str; 7] = [
+    "/health",
+    "/ready",
+    "/metrics",
+    "/api/admin",
+    "/api/v1/admin",
+    "/api/auth/",
+    "/login",
+];
+
+// Answer non-admin requests with a 503 page while maintenance mode is on
+pub async fn maintenance_mode(
+    State(state): State<AppState>,
+    req: Request,
+    next: middleware::Next,
+) -> Response {
+    if !state.maintenance.is_enabled() {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path();
+    if EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(req).await;
+    }
+
+    // Only look up the user once maintenance is on, so normal requests pay nothing
+    let (mut parts, body) = req.into_parts();
+    let user = MaybeUser::from_request_parts(&mut parts, &state)
+        .await
+        .ok()
+        .and_then(|MaybeUser(user)| user);
+
+    if user.is_some_and(|user| user.has_role("admin")) {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    let nonce = parts
+        .extensions
+        .get::<CspNonce>()
+        .map(|CspNonce(nonce)| nonce.clone())
+        .unwrap_or_default();
+
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(
+            header::RETRY_AFTER,
+            state.settings.maintenance_retry_after_secs.to_string(),
+        )],
+        Html(format!(
+            r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Down for Maintenance</title>
+            <style nonce="{nonce}">
+                body {{
+                    font-family: Arial, sans-serif;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    min-height: 100vh;
+                    padding: 20px;
+                }}
+                .container {{
+                    max-width: 500px;
+                    margin: 0 auto;
+                    background: white;
+                    border-radius: 20px;
+                    padding: 40px;
+                    box-shadow: 0 20px 60px rgba(0, 0, 0, 0.3);
+                    text-align: center;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Down for Maintenance</h1>
+                <p>We're making some improvements and will be back shortly.</p>
+            </div>
+        </body>
+        </html>
+        "#,
+            nonce = nonce
+        )),
+    )
+        .into_response()
+}
@@ -1,3 +1,19 @@
+pub mod access_log;
+pub mod admin;
 pub mod auth;
+pub mod csp;
+pub mod deprecation;
+pub mod error_reporting;
+pub mod maintenance;
+pub mod onboarding;
+pub mod terms;
 
+pub use access_log::*;
+pub use admin::*;
 pub use auth::*;
+pub use csp::*;
+pub use deprecation::*;
+pub use error_reporting::*;
+pub use maintenance::*;
+pub use onboarding::*;
+pub use terms::*;
@@ -0,0 +1,24 @@
+use axum::{
+    extract::{Request, State},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
+};
+
+use crate::errors::ApiError;
+use crate::handlers::UserProfile;
+use crate::services::onboarding::{current_step, OnboardingStep};
+use crate::state::AppState;
+
+// Keep new users on /onboarding until every step is done
+pub async fn require_onboarding(
+    State(state): State<AppState>,
+    user: UserProfile,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    if current_step(&state.db, user.id).await? != OnboardingStep::Done {
+        return Ok(Redirect::to("/onboarding").into_response());
+    }
+
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,24 @@
+use axum::{
+    extract::{Request, State},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
+};
+
+use crate::errors::ApiError;
+use crate::handlers::UserProfile;
+use crate::services::legal::pending_documents;
+use crate::state::AppState;
+
+// Send users to /terms until they have accepted the current terms and privacy policy
+pub async fn require_terms_accepted(
+    State(state): State<AppState>,
+    user: UserProfile,
+    req: Request,
+    next: middleware::Next,
+) -> Result<Response, ApiError> {
+    if !pending_documents(&state.db, user.id).await?.is_empty() {
+        return Ok(Redirect::to("/terms").into_response());
+    }
+
+    Ok(next.run(req).await)
+}
@@ -1,10 +1,18 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-#[derive(Debug, Deserialize)]
+// Returned by the userinfo endpoint and also the claim set of Google ID tokens
+#[derive(Debug, Deserialize, Serialize)]
 pub struct GoogleUserInfo {
+    pub sub: String,
     pub email: String,
-    #[allow(dead_code)]
     pub name: Option<String>,
-    #[allow(dead_code)]
+    // Whether Google has verified the address; false for some non-Gmail accounts
+    pub email_verified: Option<bool>,
+    // Google Workspace domain of the account, absent for consumer accounts
+    pub hd: Option<String>,
     pub picture: Option<String>,
+    // Any other claims, e.g. groups added by a Workspace custom schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
@@ -0,0 +1,104 @@
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::Client as ReqwestClient;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::errors::ApiError;
+use crate::oauth::GoogleUserInfo;
+
+const GOOGLE_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+const GOOGLE_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
+
+// Google rotates its signing keys roughly daily, so an hour is a safe cache lifetime
+const KEYS_TTL: Duration = Duration::from_secs(3600);
+// Unknown key ids trigger a refetch, but not more often than this
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+// Verifies Google ID tokens (e.g. One Tap credentials) against Google's published JWKS
+#[derive(Clone, Default)]
+pub struct GoogleKeys {
+    cache: Arc<RwLock<Option<CachedKeys>>>,
+}
+
+impl GoogleKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Check the signature, issuer, audience and expiry and return the token's claims
+    pub async fn verify(
+        &self,
+        ctx: &ReqwestClient,
+        credential: &str,
+        client_id: &str,
+    ) -> Result<GoogleUserInfo, ApiError> {
+        let header = decode_header(credential).map_err(|_| ApiError::Unauthorized)?;
+        let kid = header.kid.ok_or(ApiError::Unauthorized)?;
+
+        let key = match self.find(&kid).await {
+            Some(key) => key,
+            None => {
+                self.refresh(ctx).await?;
+                self.find(&kid).await.ok_or(ApiError::Unauthorized)?
+            }
+        };
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[client_id]);
+        validation.set_issuer(&GOOGLE_ISSUERS);
+
+        let token = decode::<GoogleUserInfo>(credential, &key, &validation).map_err(|e| {
+            tracing::warn!("Rejected Google ID token: {}", e);
+            ApiError::Unauthorized
+        })?;
+
+        Ok(token.claims)
+    }
+
+    async fn find(&self, kid: &str) -> Option<DecodingKey> {
+        let cache = self.cache.read().await;
+        let cached = cache.as_ref()?;
+
+        if cached.fetched_at.elapsed() > KEYS_TTL {
+            return None;
+        }
+
+        cached
+            .keys
+            .find(kid)
+            .and_then(|jwk| DecodingKey::from_jwk(jwk).ok())
+    }
+
+    async fn refresh(&self, ctx: &ReqwestClient) -> Result<(), ApiError> {
+        let mut cache = self.cache.write().await;
+
+        // Another request may have refreshed the keys while we waited for the lock
+        if cache
+            .as_ref()
+            .is_some_and(|cached| cached.fetched_at.elapsed() < MIN_REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+
+        let keys = ctx
+            .get(GOOGLE_CERTS_URL)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+
+        *cache = Some(CachedKeys {
+            keys,
+            fetched_at: Instant::now(),
+        });
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 pub mod google;
+pub mod id_token;
 pub mod twitter;
 pub mod types;
 
 pub use google::*;
+pub use id_token::*;
 pub use twitter::*;
 pub use types::*;
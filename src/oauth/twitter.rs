@@ -1,12 +1,14 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct TwitterUserData {
-    #[allow(dead_code)]
     pub id: String,
-    #[allow(dead_code)]
     pub name: String,
     pub username: String,
+    // Extra user fields requested with user.fields
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize)]
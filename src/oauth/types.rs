@@ -1,19 +1,66 @@
 use oauth2::basic::BasicClient;
+use oauth2::RedirectUrl;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::errors::ApiError;
+
 #[derive(Clone)]
 pub struct OAuthClients {
     pub google: BasicClient,
     pub twitter: BasicClient,
 }
 
-#[derive(Clone)]
-pub struct ClientIds {
-    pub google: String,
-    #[allow(dead_code)]
-    pub twitter: String,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    Google,
+    Twitter,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 2] = [Self::Google, Self::Twitter];
+
+    // Identifier stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Google => "google",
+            Self::Twitter => "twitter",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "google" => Some(Self::Google),
+            "twitter" => Some(Self::Twitter),
+            _ => None,
+        }
+    }
+
+    // Callback the provider redirects back to on the given origin
+    pub fn redirect_url(&self, origin: &str) -> RedirectUrl {
+        RedirectUrl::new(format!("{}/api/auth/{}_callback", origin, self.as_str()))
+            .expect("callback origins are validated at startup")
+    }
+
+    // Human-readable name for templates
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Google => "Google",
+            Self::Twitter => "Twitter",
+        }
+    }
+}
+
+// Identity returned by a provider after a successful login
+#[derive(Debug, Clone)]
+pub struct ProviderProfile {
+    pub provider: Provider,
+    pub subject: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    // Everything the provider said about the user, for claim mappings
+    pub claims: serde_json::Value,
 }
 
 // Store PKCE verifiers for Twitter
@@ -21,7 +68,19 @@ pub type PkceVerifiers = Arc<tokio::sync::Mutex<HashMap<String, String>>>;
 
 #[derive(Debug, Deserialize)]
 pub struct AuthRequest {
-    pub code: String,
-    #[allow(dead_code)]
+    pub code: Option<String>,
     pub state: Option<String>,
+    // Set by the provider instead of a code when authorization fails
+    pub error: Option<String>,
+}
+
+impl AuthRequest {
+    pub fn code(&self) -> Result<String, ApiError> {
+        self.code.clone().ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "Authorization failed: {}",
+                self.error.as_deref().unwrap_or("missing code")
+            ))
+        })
+    }
 }
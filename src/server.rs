@@ -0,0 +1,201 @@
+use axum::{extract::ConnectInfo, Router};
+use futures::future::try_join_all;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use ipnet::IpNet;
+use std::env;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::os::fd::{FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use tokio::net::{TcpListener, UnixListener};
+use tower::ServiceExt;
+
+use crate::config::settings::ListenAddr;
+
+// First file descriptor passed by systemd socket activation
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+// Address Unix socket peers are given. It's in the discard-only 100::/64 range
+// (RFC 6666), so no real client has it and allowlists only match it when it's
+// listed explicitly
+pub const UNIX_PEER: IpAddr = IpAddr::V6(Ipv6Addr::new(0x100, 0, 0, 0, 0, 0, 0, 0));
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl Listener {
+    pub fn describe(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => listener
+                .local_addr()
+                .map(|addr| format!("http://{}", addr))
+                .unwrap_or_else(|_| "tcp socket".to_string()),
+            Listener::Unix(listener) => listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(|path| path.display().to_string()))
+                .map_or("unix socket".to_string(), |path| format!("unix:{}", path)),
+        }
+    }
+}
+
+// Sockets handed over by systemd take the place of LISTEN, so the unit's
+// .socket file decides where the app listens and connections queue up
+// across restarts
+pub async fn bind(addrs: &[ListenAddr], trusted_proxies: &[IpNet]) -> io::Result<Vec<Listener>> {
+    let listeners = match inherited_listeners()? {
+        Some(listeners) => listeners,
+        None => bind_addrs(addrs).await?,
+    };
+
+    // Every Unix peer shares one address, so without X-Forwarded-For all clients
+    // would land in one throttling bucket
+    let has_unix = listeners
+        .iter()
+        .any(|listener| matches!(listener, Listener::Unix(_)));
+    if has_unix && !trusted_proxies.iter().any(|net| net.contains(&UNIX_PEER)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Unix socket listeners need {} in TRUSTED_PROXIES so client addresses come from X-Forwarded-For",
+                UNIX_PEER
+            ),
+        ));
+    }
+
+    Ok(listeners)
+}
+
+async fn bind_addrs(addrs: &[ListenAddr]) -> io::Result<Vec<Listener>> {
+    let mut listeners = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        listeners.push(match addr {
+            ListenAddr::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr).await?),
+            ListenAddr::Unix(path) => {
+                // A socket file left behind by a previous run would make bind fail
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                Listener::Unix(UnixListener::bind(path)?)
+            }
+        });
+    }
+
+    Ok(listeners)
+}
+
+// LISTEN_FDS/LISTEN_PID as described in sd_listen_fds(3)
+fn inherited_listeners() -> io::Result<Option<Vec<Listener>>> {
+    let for_us = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .unwrap_or(0);
+
+    // Unset them so child processes don't take the descriptors for their own
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    if !for_us || count == 0 {
+        return Ok(None);
+    }
+
+    let mut listeners = Vec::with_capacity(count as usize);
+    for fd in SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count {
+        // SAFETY: systemd passes ownership of these descriptors to this process
+        let tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+
+        // Only inet sockets have an IP address; anything else is a Unix socket
+        let listener = if tcp.local_addr().is_ok() {
+            tcp.set_nonblocking(true)?;
+            Listener::Tcp(TcpListener::from_std(tcp)?)
+        } else {
+            // SAFETY: the descriptor was released by into_raw_fd just above
+            let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+            unix.set_nonblocking(true)?;
+            Listener::Unix(UnixListener::from_std(unix)?)
+        };
+        listeners.push(listener);
+    }
+
+    Ok(Some(listeners))
+}
+
+// Tell systemd (Type=notify) that the service is up; a no-op when not run by systemd
+pub fn notify_ready() {
+    let Ok(path) = env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    let sent = UnixDatagram::unbound().and_then(|socket| {
+        // Abstract socket namespace, which only exists on Linux
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return socket.send_to_addr(b"READY=1", &addr);
+        }
+        socket.send_to(b"READY=1", &path)
+    });
+
+    if let Err(e) = sent {
+        tracing::warn!("Failed to notify systemd of readiness: {}", e);
+    }
+}
+
+// Serve the app on every listener until one of them fails
+pub async fn serve(app: Router, listeners: Vec<Listener>) -> io::Result<()> {
+    let servers = listeners.into_iter().map(|listener| {
+        let app = app.clone();
+        async move {
+            match listener {
+                Listener::Tcp(listener) => serve_tcp(app, listener).await,
+                Listener::Unix(listener) => serve_unix(app, listener).await,
+            }
+        }
+    });
+
+    try_join_all(servers).await?;
+    Ok(())
+}
+
+async fn serve_tcp(app: Router, listener: TcpListener) -> io::Result<()> {
+    // Client addresses are needed for per-IP login throttling
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+}
+
+async fn serve_unix(app: Router, listener: UnixListener) -> io::Result<()> {
+    // Unix peers have no IP; the marker address never matches an allowlist by accident
+    let peer = ConnectInfo(SocketAddr::new(UNIX_PEER, 0));
+
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let app = app.clone();
+
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(move |mut req: hyper::Request<Incoming>| {
+                req.extensions_mut().insert(peer);
+                app.clone().oneshot(req)
+            });
+
+            if let Err(e) = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(socket), service)
+                .await
+            {
+                tracing::debug!("Unix socket connection closed with error: {}", e);
+            }
+        });
+    }
+}
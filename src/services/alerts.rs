@@ -0,0 +1,299 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client as ReqwestClient;
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+use tokio::sync::mpsc;
+
+use crate::errors::ApiError;
+use crate::oauth::Provider;
+
+// Consecutive upstream failures after which a provider is reported as down
+const PROVIDER_OUTAGE_THRESHOLD: u32 = 5;
+// How often new admin role grants are looked for
+const ROLE_GRANT_POLL_INTERVAL: StdDuration = StdDuration::from_secs(15);
+// Alerts waiting to be delivered; more are dropped rather than slowing down logins
+const QUEUE_SIZE: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlertKind {
+    AdminGranted,
+    FailedLogins,
+    ImpossibleTravel,
+    ProviderOutage,
+}
+
+impl AlertKind {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "admin_granted" => Some(Self::AdminGranted),
+            "failed_logins" => Some(Self::FailedLogins),
+            "impossible_travel" => Some(Self::ImpossibleTravel),
+            "provider_outage" => Some(Self::ProviderOutage),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::AdminGranted => "admin_granted",
+            Self::FailedLogins => "failed_logins",
+            Self::ImpossibleTravel => "impossible_travel",
+            Self::ProviderOutage => "provider_outage",
+        }
+    }
+}
+
+// Payload shape expected by the receiving end
+#[derive(Clone, Copy, Debug)]
+enum WebhookFormat {
+    Slack,
+    Discord,
+    Generic,
+}
+
+impl WebhookFormat {
+    fn for_url(url: &str) -> Self {
+        if url.starts_with("https://hooks.slack.com/") {
+            Self::Slack
+        } else if url.starts_with("https://discord.com/api/webhooks/") {
+            Self::Discord
+        } else {
+            Self::Generic
+        }
+    }
+}
+
+struct Route {
+    // None sends every kind of alert to the webhook
+    kind: Option<AlertKind>,
+    url: String,
+    format: WebhookFormat,
+}
+
+struct Alert {
+    kind: AlertKind,
+    message: String,
+    raised_at: DateTime<Utc>,
+}
+
+// Notifications about high-severity security events, delivered to webhooks in the
+// background. Cheap to clone; without routes every alert is dropped.
+#[derive(Clone)]
+pub struct Alerts {
+    routes: Arc<Vec<Route>>,
+    sender: Option<mpsc::Sender<Alert>>,
+    provider_failures: Arc<Mutex<HashMap<&'static str, u32>>>,
+}
+
+impl Alerts {
+    // routes are (kind, webhook URL) pairs from ALERT_WEBHOOKS; * matches every kind
+    pub fn new(http: ReqwestClient, routes: &[(String, String)]) -> Self {
+        let routes: Vec<Route> =
+            routes
+                .iter()
+                .map(|(kind, url)| Route {
+                    kind: match kind.as_str() {
+                        "*" => None,
+                        kind => Some(AlertKind::parse(kind).unwrap_or_else(|| {
+                            panic!("Invalid ALERT_WEBHOOKS event type {}", kind)
+                        })),
+                    },
+                    url: url.clone(),
+                    format: WebhookFormat::for_url(url),
+                })
+                .collect();
+        let routes = Arc::new(routes);
+
+        let sender = if routes.is_empty() {
+            None
+        } else {
+            let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+            tokio::spawn(deliver(http, routes.clone(), receiver));
+            Some(sender)
+        };
+
+        Self {
+            routes,
+            sender,
+            provider_failures: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn wants(&self, kind: AlertKind) -> bool {
+        self.routes
+            .iter()
+            .any(|route| route.kind.map_or(true, |k| k == kind))
+    }
+
+    pub fn raise(&self, kind: AlertKind, message: String) {
+        let Some(sender) = self.sender.as_ref().filter(|_| self.wants(kind)) else {
+            return;
+        };
+
+        let alert = Alert {
+            kind,
+            message,
+            raised_at: Utc::now(),
+        };
+        if sender.try_send(alert).is_err() {
+            tracing::warn!("Alert queue is full, dropping {} alert", kind.as_str());
+        }
+    }
+
+    // Count consecutive failures talking to a provider during login and raise an
+    // alert once when they reach the threshold. Any successful call resets the count.
+    pub fn track_provider<T>(&self, provider: Provider, result: &Result<T, ApiError>) {
+        let upstream_failure = match result {
+            Ok(_) => false,
+            Err(e) => is_upstream_failure(e),
+        };
+
+        let failures = {
+            let mut failures = self.provider_failures.lock().unwrap();
+            let count = failures.entry(provider.as_str()).or_insert(0);
+            *count = if upstream_failure { *count + 1 } else { 0 };
+            *count
+        };
+
+        if failures == PROVIDER_OUTAGE_THRESHOLD {
+            self.raise(
+                AlertKind::ProviderOutage,
+                format!(
+                    "{} looks unavailable: the last {} sign-ins failed to reach it",
+                    provider.label(),
+                    failures
+                ),
+            );
+        }
+    }
+}
+
+// Network errors and unparseable responses point at the provider; rejected
+// codes and the like are the user's problem
+fn is_upstream_failure(e: &ApiError) -> bool {
+    match e {
+        ApiError::Request(_) => true,
+        ApiError::TokenError(e) => matches!(
+            e,
+            oauth2::RequestTokenError::Request(_) | oauth2::RequestTokenError::Parse(..)
+        ),
+        _ => false,
+    }
+}
+
+async fn deliver(
+    http: ReqwestClient,
+    routes: Arc<Vec<Route>>,
+    mut receiver: mpsc::Receiver<Alert>,
+) {
+    while let Some(alert) = receiver.recv().await {
+        let text = format!("[{}] {}", alert.kind.as_str(), alert.message);
+
+        for route in routes
+            .iter()
+            .filter(|route| route.kind.map_or(true, |k| k == alert.kind))
+        {
+            let body = match route.format {
+                WebhookFormat::Slack => json!({ "text": text }),
+                WebhookFormat::Discord => json!({ "content": text }),
+                WebhookFormat::Generic => json!({
+                    "kind": alert.kind.as_str(),
+                    "message": alert.message,
+                    "raised_at": alert.raised_at,
+                }),
+            };
+
+            let sent = http
+                .post(&route.url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                tracing::warn!("Failed to deliver {} alert: {}", alert.kind.as_str(), e);
+            }
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct RoleGrant {
+    id: i64,
+    user_id: i32,
+    email: Option<String>,
+}
+
+// Roles are granted directly in the database, so admin grants are picked up from
+// the events the role trigger records
+pub fn spawn_admin_grant_watcher(db: PgPool, alerts: Alerts) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(ROLE_GRANT_POLL_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = check_admin_grants(&db, &alerts).await {
+                tracing::error!("Failed to check for admin role grants: {}", e);
+            }
+        }
+    });
+}
+
+async fn check_admin_grants(db: &PgPool, alerts: &Alerts) -> Result<(), sqlx::Error> {
+    // Only grants made after alerts were switched on are reported
+    sqlx::query(
+        "INSERT INTO alert_cursor (last_event_id)
+         SELECT COALESCE(MAX(id), 0) FROM auth_events
+         ON CONFLICT (id) DO NOTHING",
+    )
+    .execute(db)
+    .await?;
+
+    let mut tx = db.begin().await?;
+
+    // Another replica holding the lock reports these
+    let cursor: Option<(i64,)> =
+        sqlx::query_as("SELECT last_event_id FROM alert_cursor FOR UPDATE SKIP LOCKED")
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some((last_event_id,)) = cursor else {
+        return Ok(());
+    };
+
+    let grants: Vec<RoleGrant> = sqlx::query_as(
+        "SELECT e.id, e.user_id, u.email
+         FROM auth_events e
+         LEFT JOIN users u ON u.id = e.user_id
+         WHERE e.id > $1 AND e.kind = 'role_granted' AND e.detail = 'admin'
+         ORDER BY e.id",
+    )
+    .bind(last_event_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let Some(last) = grants.last() else {
+        return Ok(());
+    };
+
+    for grant in &grants {
+        alerts.raise(
+            AlertKind::AdminGranted,
+            format!(
+                "Admin role granted to {} (user {})",
+                grant.email.as_deref().unwrap_or("unknown user"),
+                grant.user_id
+            ),
+        );
+    }
+
+    sqlx::query("UPDATE alert_cursor SET last_event_id = $1, updated_at = CURRENT_TIMESTAMP")
+        .bind(last.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+use crate::oauth::Provider;
+
+// Bump today's counters for a provider
+async fn increment(
+    db: &PgPool,
+    provider: Provider,
+    logins: i32,
+    signups: i32,
+    failures: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO login_stats (day, provider, logins, signups, failures)
+         VALUES (CURRENT_DATE, $1, $2, $3, $4)
+         ON CONFLICT (day, provider) DO UPDATE SET
+            logins = login_stats.logins + excluded.logins,
+            signups = login_stats.signups + excluded.signups,
+            failures = login_stats.failures + excluded.failures",
+    )
+    .bind(provider.as_str())
+    .bind(logins)
+    .bind(signups)
+    .bind(failures)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// The login has already been committed by the time it's counted, so a failure
+// here is only logged rather than failing a login that went through
+pub async fn record_login(db: &PgPool, provider: Provider, signup: bool) {
+    if let Err(e) = increment(db, provider, 1, signup as i32, 0).await {
+        tracing::error!("Failed to record login: {}", e);
+    }
+}
+
+// Failed logins are already being answered with an error, so a failure to count
+// them is only logged
+pub async fn record_failure(db: &PgPool, provider: Provider) {
+    if let Err(e) = increment(db, provider, 0, 0, 1).await {
+        tracing::error!("Failed to record login failure: {}", e);
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct LoginStats {
+    // First day of the period
+    pub period: NaiveDate,
+    pub provider: String,
+    pub logins: i64,
+    pub signups: i64,
+    pub failures: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthStats {
+    // Last 30 days
+    pub daily: Vec<LoginStats>,
+    // Last 12 weeks
+    pub weekly: Vec<LoginStats>,
+    pub active_sessions: i64,
+    // Share of failed login attempts over the last 30 days
+    pub failure_rate: f64,
+}
+
+pub async fn auth_stats(db: &PgPool) -> Result<AuthStats, sqlx::Error> {
+    let daily: Vec<LoginStats> = sqlx::query_as(
+        "SELECT day AS period, provider,
+                logins::BIGINT AS logins, signups::BIGINT AS signups, failures::BIGINT AS failures
+         FROM login_stats
+         WHERE day > CURRENT_DATE - 30
+         ORDER BY day, provider",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let weekly = sqlx::query_as(
+        "SELECT date_trunc('week', day)::DATE AS period, provider,
+                SUM(logins)::BIGINT AS logins, SUM(signups)::BIGINT AS signups,
+                SUM(failures)::BIGINT AS failures
+         FROM login_stats
+         WHERE day > CURRENT_DATE - 84
+         GROUP BY 1, 2
+         ORDER BY 1, 2",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let (active_sessions,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sessions WHERE user_id IS NOT NULL AND expires_at > NOW()",
+    )
+    .fetch_one(db)
+    .await?;
+
+    let (logins, failures) = daily.iter().fold((0, 0), |(logins, failures), row| {
+        (logins + row.logins, failures + row.failures)
+    });
+    let attempts = logins + failures;
+    let failure_rate = if attempts > 0 {
+        failures as f64 / attempts as f64
+    } else {
+        0.0
+    };
+
+    Ok(AuthStats {
+        daily,
+        weekly,
+        active_sessions,
+        failure_rate,
+    })
+}
@@ -0,0 +1,209 @@
+use reqwest::Client as ReqwestClient;
+use sqlx::PgPool;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UdpSocket;
+
+use crate::config::settings::{AuditFormat, AuditSink};
+use crate::services::auth_events::AuthEventRecord;
+
+// Events forwarded per round
+const BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("collector error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+// Forward auth events to the configured sink as they are recorded. Events are
+// read back from auth_events rather than hooked into record(), so role changes
+// written by the database trigger are exported too and rolled back events never are.
+pub fn spawn_audit_exporter(
+    db: PgPool,
+    http: ReqwestClient,
+    sink: AuditSink,
+    format: AuditFormat,
+    interval: StdDuration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match export_batch(&db, &http, &sink, format).await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!("Exported {} audit events", count),
+                // The cursor only moves on success, so the batch is retried next round
+                Err(e) => tracing::error!("Failed to export audit events: {}", e),
+            }
+        }
+    });
+}
+
+async fn export_batch(
+    db: &PgPool,
+    http: &ReqwestClient,
+    sink: &AuditSink,
+    format: AuditFormat,
+) -> Result<usize, ExportError> {
+    // Export starts with the events recorded after it was first switched on
+    sqlx::query(
+        "INSERT INTO audit_export_cursor (last_event_id)
+         SELECT COALESCE(MAX(id), 0) FROM auth_events
+         ON CONFLICT (id) DO NOTHING",
+    )
+    .execute(db)
+    .await?;
+
+    let mut tx = db.begin().await?;
+
+    // Another replica holding the lock is already exporting
+    let cursor: Option<(i64,)> =
+        sqlx::query_as("SELECT last_event_id FROM audit_export_cursor FOR UPDATE SKIP LOCKED")
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some((last_event_id,)) = cursor else {
+        return Ok(0);
+    };
+
+    // Ids are assigned before commit, so a slow transaction can commit an event
+    // below ids already seen. Holding back the newest seconds keeps those in order.
+    let events: Vec<AuthEventRecord> = sqlx::query_as(
+        "SELECT id, user_id, kind, provider, detail, ip_address, occurred_at
+         FROM auth_events
+         WHERE id > $1 AND occurred_at < NOW() - INTERVAL '5 seconds'
+         ORDER BY id
+         LIMIT $2",
+    )
+    .bind(last_event_id)
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let Some(last) = events.last() else {
+        return Ok(0);
+    };
+
+    let lines: Vec<String> = events
+        .iter()
+        .map(|event| match format {
+            AuditFormat::Json => serde_json::to_string(event).unwrap_or_default(),
+            AuditFormat::Cef => cef_line(event),
+        })
+        .collect();
+    send(http, sink, format, &events, &lines).await?;
+
+    sqlx::query(
+        "UPDATE audit_export_cursor SET last_event_id = $1, updated_at = CURRENT_TIMESTAMP",
+    )
+    .bind(last.id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(events.len())
+}
+
+async fn send(
+    http: &ReqwestClient,
+    sink: &AuditSink,
+    format: AuditFormat,
+    events: &[AuthEventRecord],
+    lines: &[String],
+) -> Result<(), ExportError> {
+    match sink {
+        AuditSink::Syslog(addr) => {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            for (event, line) in events.iter().zip(lines) {
+                // <38> is facility auth, severity informational
+                let message = format!(
+                    "<38>1 {} - oauth_axum - {} - {}",
+                    event.occurred_at.to_rfc3339(),
+                    event.kind,
+                    line
+                );
+                socket.send_to(message.as_bytes(), addr.as_str()).await?;
+            }
+        }
+        AuditSink::File(path) => {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(format!("{}\n", lines.join("\n")).as_bytes())
+                .await?;
+            file.flush().await?;
+        }
+        AuditSink::Http(url) => {
+            let content_type = match format {
+                AuditFormat::Json => "application/x-ndjson",
+                AuditFormat::Cef => "text/plain",
+            };
+            http.post(url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(lines.join("\n"))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+    }
+
+    Ok(())
+}
+
+// ArcSight Common Event Format
+fn cef_line(event: &AuthEventRecord) -> String {
+    let severity = match event.kind.as_str() {
+        "role_granted" | "role_revoked" | "user_deleted" => 7,
+        "session_revoked" | "tokens_revoked" | "user_restored" => 5,
+        _ => 3,
+    };
+
+    let mut extension = format!(
+        "rt={} externalId={} suid={}",
+        event.occurred_at.timestamp_millis(),
+        event.id,
+        event.user_id
+    );
+    if let Some(ip) = &event.ip_address {
+        extension.push_str(&format!(" src={}", cef_escape_value(ip)));
+    }
+    if let Some(provider) = &event.provider {
+        extension.push_str(&format!(
+            " cs1Label=provider cs1={}",
+            cef_escape_value(provider)
+        ));
+    }
+    if let Some(detail) = &event.detail {
+        extension.push_str(&format!(" msg={}", cef_escape_value(detail)));
+    }
+
+    format!(
+        "CEF:0|oauth_axum|oauth_axum|{}|{}|{}|{}|{}",
+        env!("CARGO_PKG_VERSION"),
+        cef_escape_header(&event.kind),
+        cef_escape_header(&event.kind.replace('_', " ")),
+        severity,
+        extension
+    )
+}
+
+fn cef_escape_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn cef_escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::net::IpAddr;
+
+use crate::oauth::Provider;
+
+// Auth state changes recorded by the application. Role grants and revocations are
+// recorded by a database trigger since roles are assigned outside the app.
+#[derive(Debug)]
+pub enum AuthEvent<'a> {
+    UserCreated {
+        provider: Provider,
+    },
+    IdentityLinked {
+        provider: Provider,
+        subject: &'a str,
+    },
+    SessionIssued {
+        provider: Provider,
+        ip: IpAddr,
+    },
+    SessionRevoked {
+        reason: &'static str,
+    },
+    UserDeleted,
+    UserRestored,
+    UserImported {
+        import_id: i32,
+    },
+    TokensRevoked {
+        provider: Provider,
+    },
+}
+
+impl AuthEvent<'_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::UserCreated { .. } => "user_created",
+            Self::IdentityLinked { .. } => "identity_linked",
+            Self::SessionIssued { .. } => "session_issued",
+            Self::SessionRevoked { .. } => "session_revoked",
+            Self::UserDeleted => "user_deleted",
+            Self::UserRestored => "user_restored",
+            Self::UserImported { .. } => "user_imported",
+            Self::TokensRevoked { .. } => "tokens_revoked",
+        }
+    }
+
+    fn provider(&self) -> Option<&'static str> {
+        match self {
+            Self::UserCreated { provider }
+            | Self::IdentityLinked { provider, .. }
+            | Self::SessionIssued { provider, .. }
+            | Self::TokensRevoked { provider } => Some(provider.as_str()),
+            _ => None,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::IdentityLinked { subject, .. } => Some(subject.to_string()),
+            Self::SessionRevoked { reason } => Some(reason.to_string()),
+            Self::UserImported { import_id } => Some(import_id.to_string()),
+            _ => None,
+        }
+    }
+
+    fn ip(&self) -> Option<String> {
+        match self {
+            Self::SessionIssued { ip, .. } => Some(ip.to_string()),
+            _ => None,
+        }
+    }
+}
+
+// Events are only ever inserted; the table rejects updates and deletes
+pub async fn record(
+    db: impl PgExecutor<'_>,
+    user_id: i32,
+    event: AuthEvent<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO auth_events (user_id, kind, provider, detail, ip_address)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(user_id)
+    .bind(event.kind())
+    .bind(event.provider())
+    .bind(event.detail())
+    .bind(event.ip())
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuthEventRecord {
+    pub id: i64,
+    pub user_id: i32,
+    pub kind: String,
+    pub provider: Option<String>,
+    pub detail: Option<String>,
+    pub ip_address: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventFilter {
+    pub user_id: Option<i32>,
+    pub kind: Option<String>,
+    // Return events older than this id, for paging backwards through the stream
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 500;
+
+// Newest events first, optionally for one user or kind
+pub async fn list_events(
+    db: &PgPool,
+    filter: &EventFilter,
+) -> Result<Vec<AuthEventRecord>, sqlx::Error> {
+    let kind = filter.kind.as_deref().filter(|kind| !kind.is_empty());
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    sqlx::query_as(
+        "SELECT id, user_id, kind, provider, detail, ip_address, occurred_at
+         FROM auth_events
+         WHERE ($1::int IS NULL OR user_id = $1)
+           AND ($2::text IS NULL OR kind = $2)
+           AND ($3::bigint IS NULL OR id < $3)
+         ORDER BY id DESC
+         LIMIT $4",
+    )
+    .bind(filter.user_id)
+    .bind(kind)
+    .bind(filter.before)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,50 @@
+use serde_json::Value;
+
+use crate::config::settings::ClaimPath;
+use crate::config::Settings;
+use crate::oauth::ProviderProfile;
+
+// The claim a path points at, unless the path is limited to another provider
+fn claim<'a>(profile: &'a ProviderProfile, path: &ClaimPath) -> Option<&'a Value> {
+    if path
+        .provider
+        .is_some_and(|provider| provider != profile.provider)
+    {
+        return None;
+    }
+    profile.claims.get(&path.claim)
+}
+
+// Strings compare as they are, lists match when any element does, and
+// booleans and numbers by their JSON text (true, 42)
+fn matches(claim: &Value, expected: &str) -> bool {
+    match claim {
+        Value::String(value) => value == expected,
+        Value::Array(values) => values.iter().any(|value| matches(value, expected)),
+        Value::Bool(_) | Value::Number(_) => claim.to_string() == expected,
+        _ => false,
+    }
+}
+
+// First DISPLAY_NAME_CLAIMS entry the provider sent a non-empty string for
+pub fn display_name(settings: &Settings, profile: &ProviderProfile) -> Option<String> {
+    settings
+        .display_name_claims
+        .iter()
+        .filter_map(|path| claim(profile, path)?.as_str())
+        .find(|name| !name.trim().is_empty())
+        .map(str::to_string)
+}
+
+// Roles from every CLAIM_ROLE_MAPPINGS entry the profile matches, without duplicates
+pub fn roles(settings: &Settings, profile: &ProviderProfile) -> Vec<String> {
+    let mut roles: Vec<String> = Vec::new();
+    for mapping in &settings.claim_role_mappings {
+        let matched =
+            claim(profile, &mapping.claim).is_some_and(|claim| matches(claim, &mapping.value));
+        if matched && !roles.contains(&mapping.role) {
+            roles.push(mapping.role.clone());
+        }
+    }
+    roles
+}
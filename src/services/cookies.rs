@@ -0,0 +1,77 @@
+use axum_extra::extract::cookie::Cookie;
+use time::Duration as TimeDuration;
+
+use crate::config::Settings;
+use crate::oauth::Provider;
+
+// Session cookie with the configured name and hardening attributes
+pub fn session_cookie(
+    settings: &Settings,
+    session_id: String,
+    max_age: TimeDuration,
+) -> Cookie<'static> {
+    build_cookie(
+        settings,
+        settings.session_cookie_name.clone(),
+        session_id,
+        max_age,
+    )
+}
+
+// Guest sessions use their own cookie so they never reach the signed-in checks
+pub fn guest_cookie_name(settings: &Settings) -> String {
+    format!("{}_guest", settings.session_cookie_name)
+}
+
+pub fn guest_cookie(
+    settings: &Settings,
+    session_id: String,
+    max_age: TimeDuration,
+) -> Cookie<'static> {
+    build_cookie(settings, guest_cookie_name(settings), session_id, max_age)
+}
+
+pub fn guest_removal_cookie(settings: &Settings) -> Cookie<'static> {
+    guest_cookie(settings, String::new(), TimeDuration::seconds(-1))
+}
+
+// Not sensitive and kept after logout, so the login page can point returning users
+// at the provider they used before instead of creating a second account
+pub fn last_provider_cookie_name(settings: &Settings) -> String {
+    format!("{}_provider", settings.session_cookie_name)
+}
+
+pub fn last_provider_cookie(settings: &Settings, provider: Provider) -> Cookie<'static> {
+    build_cookie(
+        settings,
+        last_provider_cookie_name(settings),
+        provider.as_str().to_string(),
+        TimeDuration::days(365),
+    )
+}
+
+fn build_cookie(
+    settings: &Settings,
+    name: String,
+    value: String,
+    max_age: TimeDuration,
+) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name, value))
+        .path("/")
+        .http_only(true)
+        .secure(settings.cookie_secure)
+        .same_site(settings.cookie_same_site)
+        .max_age(max_age);
+
+    // Shared with sibling subdomains when configured
+    if let Some(domain) = &settings.cookie_domain {
+        cookie = cookie.domain(domain.clone());
+    }
+
+    cookie.build()
+}
+
+// Expired session cookie that makes the browser drop the current one
+pub fn session_removal_cookie(settings: &Settings) -> Cookie<'static> {
+    session_cookie(settings, String::new(), TimeDuration::seconds(-1))
+}
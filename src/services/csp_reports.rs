@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+
+// One violation, from either the legacy report-uri format or the Reporting API
+#[derive(Debug)]
+pub struct CspViolation {
+    pub document_uri: String,
+    pub directive: String,
+    pub blocked_uri: String,
+    pub source_file: Option<String>,
+    pub line_number: Option<i32>,
+    pub sample: Option<String>,
+}
+
+// First of the given keys that holds a non-empty string
+fn text(report: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| report.get(*key)?.as_str())
+        .find(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+// Query strings and fragments can carry OAuth codes or tokens, so they are dropped
+fn without_query(uri: String) -> String {
+    match uri.find(['?', '#']) {
+        Some(end) => uri[..end].to_string(),
+        None => uri,
+    }
+}
+
+impl CspViolation {
+    fn from_report(report: &Value) -> Self {
+        Self {
+            document_uri: without_query(
+                text(report, &["document-uri", "documentURL"]).unwrap_or_default(),
+            ),
+            directive: text(
+                report,
+                &[
+                    "effective-directive",
+                    "effectiveDirective",
+                    "violated-directive",
+                ],
+            )
+            .unwrap_or_default(),
+            blocked_uri: without_query(
+                text(report, &["blocked-uri", "blockedURL"]).unwrap_or_default(),
+            ),
+            source_file: text(report, &["source-file", "sourceFile"]).map(without_query),
+            line_number: ["line-number", "lineNumber"]
+                .iter()
+                .find_map(|key| report.get(*key)?.as_i64())
+                .and_then(|line| i32::try_from(line).ok()),
+            sample: text(report, &["script-sample", "sample"]),
+        }
+    }
+}
+
+// application/csp-report bodies hold one {"csp-report": {...}} object, while
+// application/reports+json bodies are arrays of typed reports
+pub fn parse_reports(body: &Value) -> Vec<CspViolation> {
+    match body {
+        Value::Object(object) => object
+            .get("csp-report")
+            .map(CspViolation::from_report)
+            .into_iter()
+            .collect(),
+        Value::Array(reports) => reports
+            .iter()
+            .filter(|report| report.get("type").and_then(Value::as_str) == Some("csp-violation"))
+            .filter_map(|report| report.get("body"))
+            .map(CspViolation::from_report)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub async fn store_violation(
+    db: &PgPool,
+    violation: &CspViolation,
+    user_agent: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO csp_reports
+            (document_uri, directive, blocked_uri, source_file, line_number, sample, user_agent)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (document_uri, directive, blocked_uri) DO UPDATE SET
+            source_file = excluded.source_file,
+            line_number = excluded.line_number,
+            sample = excluded.sample,
+            user_agent = excluded.user_agent,
+            count = csp_reports.count + 1,
+            last_seen_at = CURRENT_TIMESTAMP",
+    )
+    .bind(&violation.document_uri)
+    .bind(&violation.directive)
+    .bind(&violation.blocked_uri)
+    .bind(&violation.source_file)
+    .bind(violation.line_number)
+    .bind(&violation.sample)
+    .bind(user_agent)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct CspReport {
+    pub id: i32,
+    pub document_uri: String,
+    pub directive: String,
+    pub blocked_uri: String,
+    pub source_file: Option<String>,
+    pub line_number: Option<i32>,
+    pub sample: Option<String>,
+    pub user_agent: Option<String>,
+    pub count: i32,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+// Most frequent violations first
+pub async fn list_reports(db: &PgPool) -> Result<Vec<CspReport>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, document_uri, directive, blocked_uri, source_file, line_number, sample,
+                user_agent, count, first_seen_at, last_seen_at
+         FROM csp_reports
+         ORDER BY count DESC, last_seen_at DESC
+         LIMIT 500",
+    )
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,32 @@
+use sqlx::PgPool;
+
+use crate::config::Settings;
+
+pub fn email_domain(email: &str) -> Option<String> {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.to_lowercase())
+}
+
+// Whether the email belongs to a disposable domain that no admin has allowed
+pub async fn is_disposable_email(
+    db: &PgPool,
+    settings: &Settings,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let Some(domain) = email_domain(email) else {
+        return Ok(false);
+    };
+
+    if !settings.disposable_email_domains.contains(&domain) {
+        return Ok(false);
+    }
+
+    let overridden: Option<(String,)> =
+        sqlx::query_as("SELECT domain FROM email_domain_overrides WHERE domain = $1")
+            .bind(&domain)
+            .fetch_optional(db)
+            .await?;
+
+    Ok(overridden.is_none())
+}
@@ -0,0 +1,188 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use sqlx::PgPool;
+use std::time::Duration as StdDuration;
+
+// Monthly partitions are created this many months ahead
+const MONTHS_AHEAD: u32 = 2;
+const MAINTENANCE_INTERVAL: StdDuration = StdDuration::from_secs(3600);
+
+// Create upcoming auth_events partitions and, when a retention period is set, drop
+// months that have aged out of it
+pub fn spawn_partition_maintainer(db: PgPool, retention_months: u32) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MAINTENANCE_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = create_partitions(&db).await {
+                tracing::error!("Failed to create auth event partitions: {}", e);
+            }
+
+            if retention_months > 0 {
+                match drop_expired_partitions(&db, retention_months).await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!("Dropped {} expired auth event partitions", count),
+                    Err(e) => tracing::error!("Failed to drop auth event partitions: {}", e),
+                }
+            }
+        }
+    });
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("first of the month exists")
+}
+
+fn add_months(month: NaiveDate, months: i32) -> NaiveDate {
+    let index = month.year() * 12 + month.month0() as i32 + months;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1)
+        .expect("valid month")
+}
+
+fn partition_name(month: NaiveDate) -> String {
+    format!("auth_events_y{}m{:02}", month.year(), month.month())
+}
+
+async fn create_partitions(db: &PgPool) -> Result<(), sqlx::Error> {
+    let current = month_start(Utc::now().date_naive());
+
+    for offset in 0..=MONTHS_AHEAD as i32 {
+        let month = add_months(current, offset);
+
+        // The partition the table was converted from already covers the month it
+        // was converted in
+        let (covered,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS (
+                SELECT 1 FROM pg_inherits i
+                JOIN pg_class c ON c.oid = i.inhrelid
+                WHERE i.inhparent = 'auth_events'::regclass
+                  AND (c.relname = $1 OR (c.relname > $2 AND c.relname LIKE 'auth_events_before_%'))
+            )",
+        )
+        .bind(partition_name(month))
+        .bind(format!(
+            "auth_events_before_y{}m{:02}",
+            month.year(),
+            month.month()
+        ))
+        .fetch_one(db)
+        .await?;
+        if covered {
+            continue;
+        }
+
+        // Names and bounds come from dates, never from input
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF auth_events FOR VALUES FROM ('{}') TO ('{}')",
+            partition_name(month),
+            month,
+            add_months(month, 1)
+        ))
+        .execute(db)
+        .await?;
+        tracing::info!("Created auth event partition {}", partition_name(month));
+    }
+
+    Ok(())
+}
+
+// Partitions whose whole range is older than the retention period
+async fn drop_expired_partitions(db: &PgPool, retention_months: u32) -> Result<usize, sqlx::Error> {
+    let cutoff = add_months(
+        month_start(Utc::now().date_naive()),
+        -(retention_months as i32),
+    );
+
+    let partitions: Vec<(String,)> = sqlx::query_as(
+        "SELECT c.relname::text FROM pg_inherits i
+         JOIN pg_class c ON c.oid = i.inhrelid
+         WHERE i.inhparent = 'auth_events'::regclass",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut dropped = 0;
+    for (name,) in partitions {
+        let Some(end) = partition_end(&name) else {
+            continue;
+        };
+        if end > cutoff {
+            continue;
+        }
+
+        sqlx::query(&format!("DROP TABLE {}", name))
+            .execute(db)
+            .await?;
+        tracing::info!("Dropped auth event partition {}", name);
+        dropped += 1;
+    }
+
+    Ok(dropped)
+}
+
+// End of the range a partition covers, from its name; the default partition has none
+fn partition_end(name: &str) -> Option<NaiveDate> {
+    let (before, date) = match name.strip_prefix("auth_events_before_") {
+        Some(date) => (true, date),
+        None => (false, name.strip_prefix("auth_events_")?),
+    };
+
+    let (year, month) = date.strip_prefix('y')?.split_once('m')?;
+    let month = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+
+    Some(if before { month } else { add_months(month, 1) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+    }
+
+    #[test]
+    fn add_months_rolls_over_the_year() {
+        assert_eq!(add_months(date(2025, 12), 1), date(2026, 1));
+        assert_eq!(add_months(date(2025, 11), 14), date(2027, 1));
+        assert_eq!(add_months(date(2025, 6), 0), date(2025, 6));
+    }
+
+    #[test]
+    fn add_months_goes_back() {
+        assert_eq!(add_months(date(2026, 1), -1), date(2025, 12));
+        assert_eq!(add_months(date(2026, 3), -15), date(2024, 12));
+        assert_eq!(add_months(date(2026, 1), -12), date(2025, 1));
+    }
+
+    #[test]
+    fn monthly_partitions_end_at_the_next_month() {
+        assert_eq!(partition_end("auth_events_y2025m06"), Some(date(2025, 7)));
+        assert_eq!(partition_end("auth_events_y2025m12"), Some(date(2026, 1)));
+        assert_eq!(
+            partition_end(&partition_name(date(2026, 2))),
+            Some(date(2026, 3))
+        );
+    }
+
+    #[test]
+    fn converted_partition_ends_at_its_own_month() {
+        assert_eq!(
+            partition_end("auth_events_before_y2025m12"),
+            Some(date(2025, 12))
+        );
+    }
+
+    #[test]
+    fn unknown_partitions_have_no_end() {
+        for name in [
+            "auth_events_default",
+            "auth_events_y2025m13",
+            "auth_events_y2025",
+            "sessions_y2025m06",
+        ] {
+            assert_eq!(partition_end(name), None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use ipnet::IpNet;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+use crate::config::Settings;
+use crate::handlers::LoginContext;
+
+// Hash of the client attributes a session is bound to, or None when binding is disabled.
+// Only the hash is stored so sessions don't keep raw network ranges around.
+pub fn session_fingerprint(settings: &Settings, context: &LoginContext) -> Option<String> {
+    if !settings.session_bind_ip_range && !settings.session_bind_user_agent {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+
+    if settings.session_bind_ip_range {
+        hasher.update(ip_range(context.ip).to_string());
+    }
+    hasher.update(b"|");
+    if settings.session_bind_user_agent {
+        hasher.update(&context.user_agent);
+    }
+
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+// Bind to the surrounding /24 (IPv4) or /64 (IPv6) so address churn within
+// a network doesn't log users out
+fn ip_range(ip: IpAddr) -> IpNet {
+    let prefix = if ip.is_ipv4() { 24 } else { 64 };
+    IpNet::new(ip, prefix).expect("valid prefix length").trunc()
+}
@@ -0,0 +1,62 @@
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use std::net::IpAddr;
+
+// Distances below this are within GeoIP accuracy and never count as travel
+const MIN_TRAVEL_DISTANCE_KM: f64 = 100.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct GeoLocation {
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+// MaxMind GeoLite2/GeoIP2 City database
+pub struct GeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl GeoIp {
+    pub fn open(path: &str) -> Result<Self, MaxMindDBError> {
+        Ok(Self {
+            reader: Reader::open_readfile(path)?,
+        })
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let record: geoip2::City = self.reader.lookup(ip).ok()?;
+
+        let location = record.location;
+
+        Some(GeoLocation {
+            country: record
+                .country
+                .and_then(|country| country.iso_code)
+                .map(str::to_string),
+            city: record
+                .city
+                .and_then(|city| city.names)
+                .and_then(|names| names.get("en").map(|name| name.to_string())),
+            latitude: location.as_ref().and_then(|l| l.latitude),
+            longitude: location.as_ref().and_then(|l| l.longitude),
+        })
+    }
+}
+
+// Great-circle distance between two coordinates
+pub fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+// Whether covering the distance in the elapsed time needs an implausible speed
+pub fn is_impossible_travel(distance_km: f64, elapsed_hours: f64, max_speed_kmh: f64) -> bool {
+    distance_km > MIN_TRAVEL_DISTANCE_KM && distance_km / elapsed_hours > max_speed_kmh
+}
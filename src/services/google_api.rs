@@ -0,0 +1,100 @@
+use reqwest::{Client as ReqwestClient, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ApiError;
+
+const CALENDAR_API_URL: &str = "https://www.googleapis.com/calendar/v3";
+
+// Calendar event in the shape returned to our own frontend
+#[derive(Debug, Serialize)]
+pub struct CalendarEvent {
+    pub id: String,
+    pub title: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub all_day: bool,
+    pub location: Option<String>,
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventList {
+    #[serde(default)]
+    items: Vec<GoogleEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleEvent {
+    id: String,
+    summary: Option<String>,
+    location: Option<String>,
+    html_link: Option<String>,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+}
+
+// Timed events carry dateTime, all-day events only a date
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventTime {
+    date_time: Option<String>,
+    date: Option<String>,
+}
+
+impl EventTime {
+    fn value(self) -> Option<String> {
+        self.date_time.or(self.date)
+    }
+}
+
+impl From<GoogleEvent> for CalendarEvent {
+    fn from(event: GoogleEvent) -> Self {
+        let all_day = event
+            .start
+            .as_ref()
+            .is_some_and(|start| start.date_time.is_none());
+
+        Self {
+            id: event.id,
+            title: event.summary.unwrap_or_else(|| "(No title)".to_string()),
+            start: event.start.and_then(EventTime::value),
+            end: event.end.and_then(EventTime::value),
+            all_day,
+            location: event.location,
+            link: event.html_link,
+        }
+    }
+}
+
+// Upcoming events from the user's primary calendar; needs the calendar.readonly scope
+pub async fn upcoming_events(
+    ctx: &ReqwestClient,
+    access_token: &str,
+) -> Result<Vec<CalendarEvent>, ApiError> {
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let response = ctx
+        .get(format!("{}/calendars/primary/events", CALENDAR_API_URL))
+        .query(&[
+            ("maxResults", "10"),
+            ("singleEvents", "true"),
+            ("orderBy", "startTime"),
+            ("timeMin", now.as_str()),
+        ])
+        .bearer_auth(access_token)
+        .send()
+        .await?;
+
+    // Google answers 403 when the scope hasn't been granted
+    if matches!(
+        response.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let events = response.error_for_status()?.json::<EventList>().await?;
+
+    Ok(events.items.into_iter().map(CalendarEvent::from).collect())
+}
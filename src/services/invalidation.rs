@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgListener;
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+
+use crate::services::Maintenance;
+
+// Postgres channel shared by all instances; the sessions trigger publishes to it too
+const CHANNEL: &str = "oauth_axum_invalidation";
+
+// Changes every instance has to apply to its in-process state
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Invalidation {
+    // SHA-256 of a revoked session id, published by the sessions trigger when the
+    // row is deleted and by session rotation
+    SessionRevoked {
+        session: String,
+        expires_at: DateTime<Utc>,
+    },
+    Maintenance {
+        enabled: bool,
+    },
+}
+
+// Stateless sessions that were deleted before they expired. Their cookies stay
+// valid on their own, so every request checks them against this list.
+#[derive(Clone, Default)]
+pub struct RevokedSessions {
+    sessions: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl RevokedSessions {
+    pub fn is_revoked(&self, session_id: &str) -> bool {
+        self.sessions
+            .lock()
+            .unwrap()
+            .contains_key(&session_hash(session_id))
+    }
+
+    fn revoke(&self, hash: String, expires_at: DateTime<Utc>) {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        // An expired cookie is rejected anyway, so its entry is no longer needed
+        let now = Utc::now();
+        sessions.retain(|_, expires_at| *expires_at > now);
+        sessions.insert(hash, expires_at);
+    }
+}
+
+fn session_hash(session_id: &str) -> String {
+    format!("{:x}", Sha256::digest(session_id.as_bytes()))
+}
+
+// Revoke a session id whose row wasn't deleted, e.g. the old id of a rotated
+// session, which the sessions trigger never sees. Inside a transaction the
+// notification is only sent on commit.
+pub async fn broadcast_session_revoked(
+    db: impl PgExecutor<'_>,
+    session_id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(&Invalidation::SessionRevoked {
+        session: session_hash(session_id),
+        expires_at,
+    })
+    .expect("invalidation serializes");
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+// Publish the maintenance flag so every instance switches, not just the one
+// that handled the admin request
+pub async fn broadcast_maintenance(db: &PgPool, enabled: bool) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(&Invalidation::Maintenance { enabled })
+        .expect("invalidation serializes");
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+// Keep this instance's caches in step with the others through LISTEN/NOTIFY, so
+// no Redis is needed. Notifications sent while the connection is down are lost.
+pub fn spawn_invalidation_listener(
+    db: PgPool,
+    maintenance: Maintenance,
+    revoked_sessions: Option<RevokedSessions>,
+) {
+    tokio::spawn(async move {
+        let mut listener = loop {
+            match subscribe(&db).await {
+                Ok(listener) => break listener,
+                Err(e) => {
+                    tracing::error!("Failed to listen for invalidations: {}", e);
+                    tokio::time::sleep(StdDuration::from_secs(5)).await;
+                }
+            }
+        };
+
+        loop {
+            let notification = match listener.try_recv().await {
+                Ok(Some(notification)) => notification,
+                // The next call reconnects and listens again
+                Ok(None) => {
+                    tracing::warn!("Invalidation channel lost, reconnecting");
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to receive invalidations: {}", e);
+                    tokio::time::sleep(StdDuration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<Invalidation>(notification.payload()) {
+                Ok(Invalidation::SessionRevoked {
+                    session,
+                    expires_at,
+                }) => {
+                    if let Some(revoked_sessions) = &revoked_sessions {
+                        revoked_sessions.revoke(session, expires_at);
+                    }
+                }
+                Ok(Invalidation::Maintenance { enabled }) => maintenance.set(enabled),
+                Err(e) => tracing::warn!("Ignoring invalid invalidation: {}", e),
+            }
+        }
+    });
+}
+
+async fn subscribe(db: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(CHANNEL).await?;
+    Ok(listener)
+}
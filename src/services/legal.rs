@@ -0,0 +1,81 @@
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct LegalDocument {
+    pub id: i32,
+    // "terms" or "privacy"
+    pub kind: String,
+    pub version: String,
+    pub content: String,
+}
+
+// Latest published version of each document kind
+pub async fn current_documents(db: &PgPool) -> Result<Vec<LegalDocument>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT DISTINCT ON (kind) id, kind, version, content
+         FROM legal_documents
+         WHERE published_at <= NOW()
+         ORDER BY kind, published_at DESC",
+    )
+    .fetch_all(db)
+    .await
+}
+
+// Current documents the user hasn't accepted yet
+pub async fn pending_documents(
+    db: &PgPool,
+    user_id: i32,
+) -> Result<Vec<LegalDocument>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, kind, version, content FROM (
+            SELECT DISTINCT ON (kind) id, kind, version, content
+            FROM legal_documents
+            WHERE published_at <= NOW()
+            ORDER BY kind, published_at DESC
+         ) current
+         WHERE NOT EXISTS (
+            SELECT 1 FROM legal_acceptances
+            WHERE legal_acceptances.user_id = $1 AND legal_acceptances.document_id = current.id
+         )
+         ORDER BY kind",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn accept_documents(
+    db: &PgPool,
+    user_id: i32,
+    document_ids: &[i32],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO legal_acceptances (user_id, document_id)
+         SELECT $1, id FROM legal_documents WHERE id = ANY($2)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(document_ids)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn publish_document(
+    db: &PgPool,
+    kind: &str,
+    version: &str,
+    content: &str,
+) -> Result<LegalDocument, sqlx::Error> {
+    sqlx::query_as(
+        "INSERT INTO legal_documents (kind, version, content) VALUES ($1, $2, $3)
+         RETURNING id, kind, version, content",
+    )
+    .bind(kind)
+    .bind(version)
+    .bind(content)
+    .fetch_one(db)
+    .await
+}
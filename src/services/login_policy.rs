@@ -0,0 +1,180 @@
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+use std::time::Duration as StdDuration;
+
+use crate::config::settings::LoginDenyRule;
+use crate::config::Settings;
+use crate::errors::ApiError;
+use crate::oauth::ProviderProfile;
+
+// Longest the policy webhook may take to answer
+const WEBHOOK_TIMEOUT: StdDuration = StdDuration::from_secs(5);
+
+#[derive(Serialize)]
+struct PolicyRequest<'a> {
+    provider: &'static str,
+    subject: &'a str,
+    email: &'a str,
+    display_name: Option<&'a str>,
+    claims: &'a Value,
+    ip: String,
+}
+
+#[derive(Deserialize)]
+struct PolicyDecision {
+    allow: bool,
+    // Shown to the user when the login is denied
+    reason: Option<String>,
+}
+
+// Runs once the provider has vouched for the user and before any account or session
+// is written, for every provider alike. A denied login shows its reason to the user.
+pub async fn check(
+    http: &ReqwestClient,
+    settings: &Settings,
+    profile: &ProviderProfile,
+    ip: IpAddr,
+) -> Result<(), ApiError> {
+    if settings
+        .login_denylist
+        .iter()
+        .any(|rule| denies(rule, profile))
+    {
+        tracing::warn!(
+            "Login by {} via {} matched LOGIN_DENYLIST",
+            profile.email,
+            profile.provider.as_str()
+        );
+        return Err(ApiError::LoginRejected(format!(
+            "{} is not allowed to sign in.",
+            profile.email
+        )));
+    }
+
+    let Some(url) = &settings.login_policy_webhook else {
+        return Ok(());
+    };
+
+    let request = PolicyRequest {
+        provider: profile.provider.as_str(),
+        subject: &profile.subject,
+        email: &profile.email,
+        display_name: profile.display_name.as_deref(),
+        claims: &profile.claims,
+        ip: ip.to_string(),
+    };
+
+    match ask_webhook(http, url, &request).await {
+        Ok(PolicyDecision { allow: true, .. }) => Ok(()),
+        Ok(PolicyDecision { reason, .. }) => {
+            tracing::warn!("Login by {} denied by the policy webhook", profile.email);
+            Err(ApiError::LoginRejected(reason.unwrap_or_else(|| {
+                format!("{} is not allowed to sign in.", profile.email)
+            })))
+        }
+        Err(e) if settings.login_policy_fail_open => {
+            tracing::error!("Login policy webhook failed, allowing login: {}", e);
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Login policy webhook failed, rejecting login: {}", e);
+            Err(ApiError::LoginRejected(
+                "Sign-in can't be checked right now. Please try again in a few minutes."
+                    .to_string(),
+            ))
+        }
+    }
+}
+
+async fn ask_webhook(
+    http: &ReqwestClient,
+    url: &str,
+    request: &PolicyRequest<'_>,
+) -> Result<PolicyDecision, reqwest::Error> {
+    http.post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+fn denies(rule: &LoginDenyRule, profile: &ProviderProfile) -> bool {
+    rule.provider
+        .map_or(true, |provider| provider == profile.provider)
+        && glob_matches(&rule.pattern, &profile.email.to_lowercase())
+}
+
+// * matches any run of characters, everything else only itself
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = value.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    // No * at all: the pattern must be the whole value
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_without_star_matches_only_itself() {
+        assert!(glob_matches("ann@example.com", "ann@example.com"));
+        assert!(!glob_matches("ann@example.com", "joann@example.com"));
+        assert!(!glob_matches("ann@example.com", "ann@example.com.evil"));
+        assert!(!glob_matches("ann@example.com", ""));
+    }
+
+    #[test]
+    fn leading_star() {
+        assert!(glob_matches("*@example.com", "ann@example.com"));
+        assert!(glob_matches("*@example.com", "@example.com"));
+        assert!(!glob_matches("*@example.com", "ann@example.com.evil"));
+    }
+
+    #[test]
+    fn trailing_star() {
+        assert!(glob_matches("admin@*", "admin@example.com"));
+        assert!(glob_matches("admin@*", "admin@"));
+        assert!(!glob_matches("admin@*", "sysadmin@example.com"));
+    }
+
+    #[test]
+    fn several_stars() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "ann@example.com"));
+        assert!(glob_matches("*@*.example.com", "ann@mail.example.com"));
+        assert!(!glob_matches("*@*.example.com", "ann@example.com"));
+        assert!(glob_matches("a**b", "ab"));
+        assert!(glob_matches("*a*b*", "xaybz"));
+        assert!(!glob_matches("*a*b*", "xbya"));
+    }
+
+    #[test]
+    fn parts_do_not_overlap() {
+        assert!(!glob_matches("a*a", "a"));
+        assert!(glob_matches("a*a", "aa"));
+        assert!(!glob_matches("ab*bc", "abc"));
+        assert!(glob_matches("ab*bc", "abbc"));
+        assert!(!glob_matches("*a*a", "a"));
+    }
+}
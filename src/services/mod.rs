@@ -1,3 +1,38 @@
+pub mod alerts;
+pub mod analytics;
+pub mod audit_export;
+pub mod auth_events;
+pub mod claim_mapping;
+pub mod cookies;
+pub mod csp_reports;
+pub mod email_policy;
+pub mod event_partitions;
+pub mod fingerprint;
+pub mod geoip;
+pub mod google_api;
+pub mod invalidation;
+pub mod legal;
+pub mod login_policy;
+pub mod maintenance;
+pub mod onboarding;
+pub mod pool_metrics;
+pub mod post_login;
+pub mod preferences;
+pub mod provider_tokens;
+pub mod providers;
+pub mod registration;
+pub mod retention;
+pub mod schema;
 pub mod session;
+pub mod stateless_session;
+pub mod support_access;
+pub mod throttle;
+pub mod twitter_api;
+pub mod user_import;
 
+pub use alerts::Alerts;
+pub use geoip::GeoIp;
+pub use invalidation::RevokedSessions;
+pub use maintenance::Maintenance;
 pub use session::*;
+pub use throttle::{LoginThrottle, RateLimiter};
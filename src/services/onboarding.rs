@@ -0,0 +1,62 @@
+use sqlx::PgPool;
+
+// Steps a new user goes through before reaching /protected. Two-factor setup and
+// organization creation/join would slot in after Profile once those features exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingStep {
+    Profile,
+    Done,
+}
+
+impl OnboardingStep {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Profile => "profile",
+            Self::Done => "done",
+        }
+    }
+
+    // Unknown values (e.g. a step that was removed) count as finished
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "profile" => Self::Profile,
+            _ => Self::Done,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Profile | Self::Done => Self::Done,
+        }
+    }
+}
+
+pub async fn current_step(db: &PgPool, user_id: i32) -> Result<OnboardingStep, sqlx::Error> {
+    let (step,): (String,) = sqlx::query_as("SELECT onboarding_step FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+
+    Ok(OnboardingStep::parse(&step))
+}
+
+// Save the profile step and move on to the following one
+pub async fn complete_profile(
+    db: &PgPool,
+    user_id: i32,
+    display_name: &str,
+) -> Result<OnboardingStep, sqlx::Error> {
+    let next = OnboardingStep::Profile.next();
+
+    sqlx::query(
+        "UPDATE users SET display_name = $2, onboarding_step = $3, last_updated = CURRENT_TIMESTAMP
+         WHERE id = $1",
+    )
+    .bind(user_id)
+    .bind(display_name)
+    .bind(next.as_str())
+    .execute(db)
+    .await?;
+
+    Ok(next)
+}
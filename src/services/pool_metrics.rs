@@ -0,0 +1,119 @@
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+
+const SAMPLE_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+// Upper bounds of the acquire wait histogram, in seconds
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+// Requests that gave up waiting for a connection, counted where the error is answered
+static ACQUIRE_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_acquire_timeout() {
+    ACQUIRE_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+}
+
+// How long it takes to get a connection from the pool. sqlx has no hook around
+// acquire, so a sampler measures it periodically; under saturation the samples
+// wait like every request does.
+#[derive(Clone, Default)]
+pub struct PoolMetrics {
+    buckets: Arc<[AtomicU64; BUCKETS.len()]>,
+    count: Arc<AtomicU64>,
+    // Sum of waits in microseconds
+    sum_micros: Arc<AtomicU64>,
+}
+
+impl PoolMetrics {
+    fn observe(&self, wait: StdDuration) {
+        let seconds = wait.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self, db: &PgPool, max_connections: u32) -> String {
+        let size = db.size();
+        let idle = db.num_idle() as u32;
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP db_pool_connections Open connections by state");
+        let _ = writeln!(out, "# TYPE db_pool_connections gauge");
+        let _ = writeln!(out, "db_pool_connections{{state=\"idle\"}} {}", idle);
+        let _ = writeln!(
+            out,
+            "db_pool_connections{{state=\"in_use\"}} {}",
+            size.saturating_sub(idle)
+        );
+        let _ = writeln!(out, "# HELP db_pool_max_connections Configured pool size");
+        let _ = writeln!(out, "# TYPE db_pool_max_connections gauge");
+        let _ = writeln!(out, "db_pool_max_connections {}", max_connections);
+
+        let _ = writeln!(
+            out,
+            "# HELP db_pool_acquire_seconds Sampled wait for a pooled connection"
+        );
+        let _ = writeln!(out, "# TYPE db_pool_acquire_seconds histogram");
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "db_pool_acquire_seconds_bucket{{le=\"{}\"}} {}",
+                bound,
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "db_pool_acquire_seconds_bucket{{le=\"+Inf\"}} {}",
+            count
+        );
+        let _ = writeln!(
+            out,
+            "db_pool_acquire_seconds_sum {}",
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(out, "db_pool_acquire_seconds_count {}", count);
+
+        let _ = writeln!(
+            out,
+            "# HELP db_pool_acquire_timeouts_total Requests answered with 503 because no connection was free"
+        );
+        let _ = writeln!(out, "# TYPE db_pool_acquire_timeouts_total counter");
+        let _ = writeln!(
+            out,
+            "db_pool_acquire_timeouts_total {}",
+            ACQUIRE_TIMEOUTS.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+pub fn spawn_pool_sampler(db: PgPool, metrics: PoolMetrics) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            let started = Instant::now();
+            match db.acquire().await {
+                Ok(conn) => {
+                    metrics.observe(started.elapsed());
+                    drop(conn);
+                }
+                Err(e) => tracing::warn!("Pool sampler could not get a connection: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,58 @@
+use axum::async_trait;
+use oauth2::basic::BasicTokenResponse;
+use std::sync::Arc;
+
+use crate::handlers::UserProfile;
+use crate::oauth::ProviderProfile;
+use crate::state::AppState;
+
+// A login that has been committed, as handed to post-login hooks
+pub struct CompletedLogin<'a> {
+    pub user: &'a UserProfile,
+    // Row id in user_identities and the provider profile it was created from
+    pub identity_id: i32,
+    pub identity: &'a ProviderProfile,
+    // Absent for logins without a token exchange (Google One Tap)
+    pub token: Option<&'a BasicTokenResponse>,
+    // The user was created by this login
+    pub signup: bool,
+}
+
+// Runs after every successful login, for provisioning rows in the embedder's own
+// tables, syncing a CRM or queueing a welcome email. Registered through init_router.
+#[async_trait]
+pub trait PostLoginHook: Send + Sync {
+    async fn after_login(&self, state: &AppState, login: &CompletedLogin<'_>)
+        -> anyhow::Result<()>;
+}
+
+// Hooks registered with init_router, the only place that can set them. AppState
+// literals start from PostLoginHooks::default(), which runs none.
+#[derive(Clone)]
+pub struct PostLoginHooks(Arc<[Arc<dyn PostLoginHook>]>);
+
+impl PostLoginHooks {
+    pub(crate) fn new(hooks: Vec<Arc<dyn PostLoginHook>>) -> Self {
+        PostLoginHooks(hooks.into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Default for PostLoginHooks {
+    fn default() -> Self {
+        PostLoginHooks::new(Vec::new())
+    }
+}
+
+// Hooks run in registration order. The login is already committed, so a failing
+// hook is logged and the user is signed in regardless.
+pub async fn run_hooks(state: &AppState, login: &CompletedLogin<'_>) {
+    for hook in state.post_login_hooks.0.iter() {
+        if let Err(e) = hook.after_login(state, login).await {
+            tracing::error!("Post-login hook failed for user {}: {:#}", login.user.id, e);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeMap;
+
+use crate::errors::ApiError;
+use crate::handlers::LoginContext;
+
+const MAX_KEY_LENGTH: usize = 64;
+const MAX_VALUE_LENGTH: usize = 1024;
+
+// Who preferences are stored for: a signed-in user or a guest session (by row id)
+#[derive(Debug, Clone, Copy)]
+pub enum PreferenceOwner {
+    User(i32),
+    Guest(i32),
+}
+
+// Store a new guest session under the id sent in the guest cookie
+pub async fn start_guest_session(
+    db: &PgPool,
+    context: &LoginContext,
+    session_id: &str,
+    lifetime_days: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO sessions (user_id, session_id, expires_at, ip_address, user_agent)
+         VALUES (NULL, $1, NOW() + make_interval(days => $2), $3, $4)",
+    )
+    .bind(session_id)
+    .bind(lifetime_days)
+    .bind(context.ip.to_string())
+    .bind(&context.user_agent)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// Row id of a live guest session
+pub async fn guest_session(
+    db: impl sqlx::PgExecutor<'_>,
+    session_id: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let guest: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM sessions
+         WHERE session_id = $1 AND user_id IS NULL AND expires_at > NOW()",
+    )
+    .bind(session_id)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(guest.map(|(id,)| id))
+}
+
+pub async fn list_preferences(
+    db: &PgPool,
+    owner: PreferenceOwner,
+) -> Result<BTreeMap<String, String>, sqlx::Error> {
+    let rows: Vec<(String, String)> = match owner {
+        PreferenceOwner::User(id) => {
+            sqlx::query_as("SELECT key, value FROM user_preferences WHERE user_id = $1")
+                .bind(id)
+                .fetch_all(db)
+                .await?
+        }
+        PreferenceOwner::Guest(id) => {
+            sqlx::query_as("SELECT key, value FROM session_preferences WHERE session_id = $1")
+                .bind(id)
+                .fetch_all(db)
+                .await?
+        }
+    };
+
+    Ok(rows.into_iter().collect())
+}
+
+pub async fn set_preference(
+    db: &PgPool,
+    owner: PreferenceOwner,
+    key: &str,
+    value: &str,
+) -> Result<(), ApiError> {
+    let valid_key = !key.is_empty()
+        && key.len() <= MAX_KEY_LENGTH
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'));
+
+    if !valid_key || value.chars().count() > MAX_VALUE_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Preference keys are up to {} characters of a-z, 0-9, _, - and .; values up to {}",
+            MAX_KEY_LENGTH, MAX_VALUE_LENGTH
+        )));
+    }
+
+    let (table, column, id) = match owner {
+        PreferenceOwner::User(id) => ("user_preferences", "user_id", id),
+        PreferenceOwner::Guest(id) => ("session_preferences", "session_id", id),
+    };
+
+    // Table and column names come from the enum, never from the request
+    sqlx::query(&format!(
+        "INSERT INTO {0} ({1}, key, value) VALUES ($1, $2, $3)
+         ON CONFLICT ({1}, key) DO UPDATE SET value = excluded.value",
+        table, column
+    ))
+    .bind(id)
+    .bind(key)
+    .bind(value)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// Hand a guest's data to the user who just signed in. Values the user already
+// has win; the guest session itself is removed along with its preferences.
+pub async fn upgrade_guest(
+    db: &mut PgConnection,
+    guest_session_id: &str,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let Some(guest_id) = guest_session(&mut *db, guest_session_id).await? else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        "INSERT INTO user_preferences (user_id, key, value)
+         SELECT $1, key, value FROM session_preferences WHERE session_id = $2
+         ON CONFLICT (user_id, key) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(guest_id)
+    .execute(&mut *db)
+    .await?;
+
+    sqlx::query("DELETE FROM sessions WHERE id = $1")
+        .bind(guest_id)
+        .execute(&mut *db)
+        .await?;
+
+    Ok(true)
+}
@@ -0,0 +1,267 @@
+use chrono::{DateTime, Duration, Utc};
+use oauth2::{
+    basic::BasicClient, reqwest::async_http_client, AccessToken, RefreshToken, RequestTokenError,
+    StandardRevocableToken, TokenResponse,
+};
+use sqlx::{FromRow, PgPool};
+use std::time::Duration as StdDuration;
+
+use crate::errors::ApiError;
+use crate::oauth::{OAuthClients, Provider};
+use crate::services::auth_events::{self, AuthEvent};
+
+// Refresh a little early so a token doesn't expire halfway through an API call
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+// Provider tokens stored on a user's identity
+#[derive(Debug, FromRow)]
+pub struct IdentityToken {
+    pub id: i32,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl IdentityToken {
+    pub async fn load(
+        db: &PgPool,
+        user_id: i32,
+        provider: Provider,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, provider, provider_user_id, access_token, refresh_token, token_expires_at
+             FROM user_identities
+             WHERE user_id = $1 AND provider = $2 AND access_token IS NOT NULL
+             ORDER BY last_login_at DESC NULLS LAST
+             LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(provider.as_str())
+        .fetch_optional(db)
+        .await
+    }
+
+    fn expires_soon(&self) -> bool {
+        self.token_expires_at
+            .is_some_and(|at| at <= Utc::now() + Duration::seconds(EXPIRY_MARGIN_SECS))
+    }
+
+    // Return a usable access token, refreshing it first when it is about to expire
+    pub async fn access_token(
+        &mut self,
+        db: &PgPool,
+        client: &BasicClient,
+    ) -> Result<String, ApiError> {
+        if self.expires_soon() {
+            self.refresh(db, client).await?;
+        }
+
+        self.access_token.clone().ok_or(ApiError::Unauthorized)
+    }
+
+    // Exchange the refresh token and store the new tokens on the identity
+    pub async fn refresh(&mut self, db: &PgPool, client: &BasicClient) -> Result<(), ApiError> {
+        let Some(refresh_token) = self.refresh_token.clone() else {
+            return Err(ApiError::Unauthorized);
+        };
+
+        let token = client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(async_http_client)
+            .await?;
+
+        self.access_token = Some(token.access_token().secret().clone());
+        // Some providers (Twitter) rotate refresh tokens on every use
+        if let Some(refresh_token) = token.refresh_token() {
+            self.refresh_token = Some(refresh_token.secret().clone());
+        }
+        self.token_expires_at = token
+            .expires_in()
+            .map(|d| Utc::now() + Duration::seconds(d.as_secs() as i64));
+
+        sqlx::query(
+            "UPDATE user_identities
+             SET access_token = $2, refresh_token = $3, token_expires_at = $4
+             WHERE id = $1",
+        )
+        .bind(self.id)
+        .bind(&self.access_token)
+        .bind(&self.refresh_token)
+        .bind(self.token_expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+// A provider identity as shown to its owner on the connections page
+#[derive(Debug, FromRow)]
+pub struct Connection {
+    pub id: i32,
+    pub provider: String,
+    pub scopes: Vec<String>,
+    // Whether the app can still call the provider's API for this identity
+    pub has_tokens: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_connections(db: &PgPool, user_id: i32) -> Result<Vec<Connection>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, provider, scopes,
+                (access_token IS NOT NULL OR refresh_token IS NOT NULL) AS has_tokens,
+                created_at, last_login_at
+         FROM user_identities WHERE user_id = $1 ORDER BY id",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+// Revoke the stored grant at the provider and forget the tokens, so the app can no
+// longer call the provider's API for this user. Signing in with the identity keeps
+// working. Returns false when the identity doesn't belong to the user.
+pub async fn revoke_connection(
+    db: &PgPool,
+    oauth_clients: &OAuthClients,
+    user_id: i32,
+    identity_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let identity: Option<IdentityToken> = sqlx::query_as(
+        "SELECT id, provider, provider_user_id, access_token, refresh_token, token_expires_at
+         FROM user_identities WHERE id = $1 AND user_id = $2",
+    )
+    .bind(identity_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+
+    let Some(identity) = identity else {
+        return Ok(false);
+    };
+    let Some(provider) = Provider::parse(&identity.provider) else {
+        return Ok(false);
+    };
+
+    // Revoking the refresh token ends the whole grant; otherwise revoke the access token
+    let token = match (identity.refresh_token, identity.access_token) {
+        (Some(token), _) => Some(StandardRevocableToken::RefreshToken(RefreshToken::new(
+            token,
+        ))),
+        (None, Some(token)) => Some(StandardRevocableToken::AccessToken(AccessToken::new(token))),
+        (None, None) => None,
+    };
+
+    // Best effort: the local tokens are dropped even when the provider can't be reached
+    if let Some(token) = token {
+        let client = match provider {
+            Provider::Google => &oauth_clients.google,
+            Provider::Twitter => &oauth_clients.twitter,
+        };
+
+        let result = match client.revoke_token(token) {
+            Ok(request) => request
+                .request_async(async_http_client)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to revoke tokens of identity {}: {}", identity.id, e);
+        }
+    }
+
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        "UPDATE user_identities
+         SET access_token = NULL, refresh_token = NULL, token_expires_at = NULL, scopes = '{}'
+         WHERE id = $1",
+    )
+    .bind(identity.id)
+    .execute(&mut *tx)
+    .await?;
+
+    auth_events::record(&mut *tx, user_id, AuthEvent::TokensRevoked { provider }).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+// Identities refreshed per run, so one slow provider can't stall the job for long
+const REFRESH_BATCH_SIZE: i64 = 100;
+
+// Refresh tokens expiring within `window` for users with an active session, so API
+// calls don't pay the refresh on the hot path. Returns the number refreshed.
+pub async fn refresh_expiring_tokens(
+    db: &PgPool,
+    oauth_clients: &OAuthClients,
+    window: StdDuration,
+) -> Result<usize, sqlx::Error> {
+    let identities: Vec<IdentityToken> = sqlx::query_as(
+        "SELECT id, provider, provider_user_id, access_token, refresh_token, token_expires_at
+         FROM user_identities
+         WHERE refresh_token IS NOT NULL
+           AND token_expires_at < NOW() + make_interval(secs => $1)
+           AND EXISTS (
+               SELECT 1 FROM sessions
+               WHERE sessions.user_id = user_identities.user_id AND sessions.expires_at > NOW()
+           )
+         ORDER BY token_expires_at
+         LIMIT $2",
+    )
+    .bind(window.as_secs() as f64)
+    .bind(REFRESH_BATCH_SIZE)
+    .fetch_all(db)
+    .await?;
+
+    let mut refreshed = 0;
+
+    for mut identity in identities {
+        let client = match Provider::parse(&identity.provider) {
+            Some(Provider::Google) => &oauth_clients.google,
+            Some(Provider::Twitter) => &oauth_clients.twitter,
+            None => continue,
+        };
+
+        match identity.refresh(db, client).await {
+            Ok(()) => refreshed += 1,
+            // The provider rejected the refresh token (revoked or expired); stop retrying it
+            Err(ApiError::TokenError(RequestTokenError::ServerResponse(e))) => {
+                tracing::warn!("Refresh token for identity {} rejected: {}", identity.id, e);
+                sqlx::query("UPDATE user_identities SET refresh_token = NULL WHERE id = $1")
+                    .bind(identity.id)
+                    .execute(db)
+                    .await?;
+            }
+            Err(e) => tracing::warn!("Failed to refresh identity {}: {}", identity.id, e),
+        }
+    }
+
+    Ok(refreshed)
+}
+
+pub fn spawn_token_refresher(
+    db: PgPool,
+    oauth_clients: OAuthClients,
+    interval: StdDuration,
+    window: StdDuration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match refresh_expiring_tokens(&db, &oauth_clients, window).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Refreshed {} provider tokens", count),
+                Err(e) => tracing::error!("Failed to refresh provider tokens: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,57 @@
+use sqlx::PgExecutor;
+
+use crate::errors::ApiError;
+use crate::oauth::Provider;
+
+// Providers that have not been switched off by an admin
+pub async fn enabled_providers(db: impl PgExecutor<'_>) -> Result<Vec<Provider>, sqlx::Error> {
+    let disabled: Vec<(String,)> =
+        sqlx::query_as("SELECT provider FROM provider_settings WHERE NOT enabled")
+            .fetch_all(db)
+            .await?;
+
+    Ok(Provider::ALL
+        .into_iter()
+        .filter(|provider| !disabled.iter().any(|(name,)| name == provider.as_str()))
+        .collect())
+}
+
+pub async fn is_enabled(db: impl PgExecutor<'_>, provider: Provider) -> Result<bool, sqlx::Error> {
+    let enabled: Option<(bool,)> =
+        sqlx::query_as("SELECT enabled FROM provider_settings WHERE provider = $1")
+            .bind(provider.as_str())
+            .fetch_optional(db)
+            .await?;
+
+    Ok(enabled.map_or(true, |(enabled,)| enabled))
+}
+
+// Reject logins through a provider that is currently switched off
+pub async fn ensure_enabled(db: impl PgExecutor<'_>, provider: Provider) -> Result<(), ApiError> {
+    if is_enabled(db, provider).await? {
+        Ok(())
+    } else {
+        Err(ApiError::LoginRejected(format!(
+            "Sign-in with {} is temporarily disabled.",
+            provider.label()
+        )))
+    }
+}
+
+pub async fn set_enabled(
+    db: impl PgExecutor<'_>,
+    provider: Provider,
+    enabled: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO provider_settings (provider, enabled) VALUES ($1, $2)
+         ON CONFLICT (provider) DO UPDATE
+         SET enabled = excluded.enabled, updated_at = CURRENT_TIMESTAMP",
+    )
+    .bind(provider.as_str())
+    .bind(enabled)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
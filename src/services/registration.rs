@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::config::settings::RegistrationMode;
+use crate::config::Settings;
+use crate::oauth::ProviderProfile;
+use crate::services::email_policy::email_domain;
+
+// Whether someone without an account may create one by signing in. Existing
+// users are never affected.
+pub async fn may_register(
+    db: &PgPool,
+    settings: &Settings,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM users WHERE email = $1)")
+        .bind(email)
+        .fetch_one(db)
+        .await?;
+
+    let email = email.to_lowercase();
+
+    // Bootstrap admins must never be locked out of a fresh deployment
+    if exists
+        || settings.registration_mode == RegistrationMode::Open
+        || settings.admin_emails.contains(&email)
+    {
+        return Ok(true);
+    }
+
+    // Invitations don't count either; only accounts that already exist sign in
+    if settings.registration_mode == RegistrationMode::Closed {
+        return Ok(false);
+    }
+
+    // Allowlist entries are full addresses or @domain
+    if settings.registration_mode == RegistrationMode::Allowlist {
+        let domain = email_domain(&email).map(|domain| format!("@{}", domain));
+        let allowed = settings
+            .registration_allowlist
+            .iter()
+            .any(|entry| *entry == email || Some(entry) == domain.as_ref());
+
+        if allowed {
+            return Ok(true);
+        }
+    }
+
+    let (invited,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM invitations WHERE email = $1 AND accepted_at IS NULL)",
+    )
+    .bind(&email)
+    .fetch_one(db)
+    .await?;
+
+    Ok(invited)
+}
+
+// Remember who asked for access so admins can invite them
+pub async fn request_access(db: &PgPool, profile: &ProviderProfile) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO access_requests (email, provider, display_name) VALUES ($1, $2, $3)
+         ON CONFLICT (email) DO UPDATE SET
+            provider = excluded.provider,
+            display_name = excluded.display_name,
+            requested_at = CURRENT_TIMESTAMP",
+    )
+    .bind(profile.email.to_lowercase())
+    .bind(profile.provider.as_str())
+    .bind(&profile.display_name)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// Called when a new account is created; the invitation and request are used up
+pub async fn accept_invitation(db: &mut PgConnection, email: &str) -> Result<(), sqlx::Error> {
+    let email = email.to_lowercase();
+
+    sqlx::query(
+        "UPDATE invitations SET accepted_at = NOW() WHERE email = $1 AND accepted_at IS NULL",
+    )
+    .bind(&email)
+    .execute(&mut *db)
+    .await?;
+
+    sqlx::query("DELETE FROM access_requests WHERE email = $1")
+        .bind(&email)
+        .execute(&mut *db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn invite(db: &PgPool, email: &str, invited_by: i32) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO invitations (email, invited_by) VALUES ($1, $2)
+         ON CONFLICT (email) DO UPDATE SET
+            invited_by = excluded.invited_by,
+            created_at = CURRENT_TIMESTAMP,
+            accepted_at = NULL",
+    )
+    .bind(email.to_lowercase())
+    .bind(invited_by)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn revoke_invitation(db: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let revoked = sqlx::query("DELETE FROM invitations WHERE email = $1 AND accepted_at IS NULL")
+        .bind(email.to_lowercase())
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    Ok(revoked > 0)
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AccessRequest {
+    pub email: String,
+    pub provider: String,
+    pub display_name: Option<String>,
+    pub requested_at: DateTime<Utc>,
+}
+
+pub async fn access_requests(db: &PgPool) -> Result<Vec<AccessRequest>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT email, provider, display_name, requested_at FROM access_requests
+         ORDER BY requested_at DESC",
+    )
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,54 @@
+use sqlx::PgPool;
+use std::time::Duration as StdDuration;
+
+// Permanently remove users that were soft-deleted more than `retention_days` ago.
+// Identities and devices cascade; sessions have no cascade and are removed first.
+pub async fn purge_deleted_users(db: &PgPool, retention_days: i32) -> Result<u64, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        "DELETE FROM sessions WHERE user_id IN (
+            SELECT id FROM users WHERE deleted_at < NOW() - make_interval(days => $1)
+        )",
+    )
+    .bind(retention_days)
+    .execute(&mut *tx)
+    .await?;
+
+    // Recorded in the same transaction since the users are gone afterwards
+    sqlx::query(
+        "INSERT INTO auth_events (user_id, kind)
+         SELECT id, 'user_purged' FROM users
+         WHERE deleted_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(retention_days)
+    .execute(&mut *tx)
+    .await?;
+
+    let purged =
+        sqlx::query("DELETE FROM users WHERE deleted_at < NOW() - make_interval(days => $1)")
+            .bind(retention_days)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(purged)
+}
+
+pub fn spawn_user_purger(db: PgPool, interval: StdDuration, retention_days: i32) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match purge_deleted_users(&db, retention_days).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Purged {} deleted users", count),
+                Err(e) => tracing::error!("Failed to purge deleted users: {}", e),
+            }
+        }
+    });
+}
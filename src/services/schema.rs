@@ -0,0 +1,80 @@
+use anyhow::{bail, Result};
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+use std::time::{Duration as StdDuration, Instant};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Key of the advisory lock held while migrating ("oauth_mg" in ASCII)
+const MIGRATION_LOCK_KEY: i64 = 0x6f61_7574_685f_6d67;
+
+// Replicas starting together take turns: one migrates while the others wait for the
+// lock and then find nothing left to apply. sqlx locks around each run as well; this
+// lock bounds the wait and says why startup is stalled.
+pub async fn run_migrations(db: &PgPool, lock_timeout: StdDuration) -> Result<()> {
+    // Advisory locks belong to a session, so lock, migrate and unlock on one connection
+    let mut conn = db.acquire().await?;
+    let started = Instant::now();
+
+    loop {
+        let (locked,): (bool,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .fetch_one(&mut *conn)
+            .await?;
+        if locked {
+            break;
+        }
+
+        if started.elapsed() >= lock_timeout {
+            bail!(
+                "Another instance has been running migrations for over {}s",
+                lock_timeout.as_secs()
+            );
+        }
+        tracing::info!("Waiting for another instance to finish migrating");
+        tokio::time::sleep(StdDuration::from_secs(2)).await;
+    }
+
+    // Schema changes may take longer than DATABASE_STATEMENT_TIMEOUT_MS allows requests
+    let result = match sqlx::query("SET statement_timeout = 0")
+        .execute(&mut *conn)
+        .await
+    {
+        Ok(_) => MIGRATOR.run(&mut *conn).await.map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+
+    // Clean up whatever happened above; a connection that can't be cleaned up is
+    // closed rather than returned to the pool still holding the lock
+    let reset = sqlx::query("RESET statement_timeout")
+        .execute(&mut *conn)
+        .await;
+    let unlock = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await;
+    if reset.is_err() || unlock.is_err() {
+        drop(conn.detach());
+    }
+
+    result?;
+    reset?;
+    unlock?;
+    Ok(())
+}
+
+// Newest migration this build ships with
+pub fn latest_version() -> i64 {
+    MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0)
+}
+
+// Newest migration applied to the database, None before the first one
+pub async fn applied_version(db: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+        .fetch_one(db)
+        .await
+}
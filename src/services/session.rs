@@ -1,88 +1,924 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Redirect},
+    Form, Json,
 };
-use axum_extra::extract::cookie::{Cookie, PrivateCookieJar};
-use chrono::{Duration, Local};
-use oauth2::TokenResponse;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use chrono::{DateTime, Duration, Local, Utc};
+use oauth2::{basic::BasicTokenResponse, url::Url, CsrfToken, TokenResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::time::Duration as StdDuration;
 use time::Duration as TimeDuration;
 
+use crate::config::settings::{is_local_path, RegistrationMode, SessionMode};
+use crate::config::Settings;
 use crate::errors::ApiError;
+use crate::handlers::{LoginContext, UserProfile};
+use crate::oauth::ProviderProfile;
+use crate::services::alerts::AlertKind;
+use crate::services::analytics;
+use crate::services::auth_events::{self, AuthEvent};
+use crate::services::claim_mapping;
+use crate::services::cookies::{
+    guest_cookie_name, guest_removal_cookie, last_provider_cookie, session_cookie,
+    session_removal_cookie,
+};
+use crate::services::fingerprint::session_fingerprint;
+use crate::services::geoip::{self, GeoLocation};
+use crate::services::invalidation;
+use crate::services::login_policy;
+use crate::services::post_login::{self, CompletedLogin};
+use crate::services::preferences;
+use crate::services::providers;
+use crate::services::registration;
+use crate::services::stateless_session::{self, SessionClaims};
 use crate::state::AppState;
 
+// Create the user, identity and session for a completed login and return the jar
+// holding the new session cookie, along with the user's landing page
+#[tracing::instrument(level = "debug", skip_all, fields(provider = profile.provider.as_str()))]
 pub async fn store_user_session(
-    State(state): State<AppState>,
+    state: &AppState,
     jar: PrivateCookieJar,
-    email: String,
-    token: impl TokenResponse<oauth2::basic::BasicTokenType>,
-) -> Result<impl IntoResponse, ApiError> {
+    mut profile: ProviderProfile,
+    context: LoginContext,
+    token: Option<&BasicTokenResponse>,
+) -> Result<(PrivateCookieJar, Redirect), ApiError> {
     // Calculate session expiry
     let secs = token
-        .expires_in()
+        .and_then(|token| token.expires_in())
         .map(|d| d.as_secs() as i64)
-        .unwrap_or(3600); // Default to 1 hour if not provided
+        .unwrap_or(3600); // Default to 1 hour if not provided (e.g. One Tap logins)
 
     let max_age = Local::now().naive_local() + Duration::seconds(secs);
 
-    // Generate a session ID
-    let session_id = format!("{}:{}", email, token.access_token().secret());
+    // Generate a random session ID
+    let session_id = CsrfToken::new_random().secret().clone();
 
-    // Create secure cookie with expiration
-    let cookie = Cookie::build(("sid", session_id.clone()))
-        .path("/")
-        .http_only(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Lax)
-        .max_age(TimeDuration::seconds(secs));
+    // Logins already in flight when an admin disables the provider stop here
+    providers::ensure_enabled(&state.db, profile.provider).await?;
 
-    // Store user in database
-    sqlx::query(
-        "INSERT INTO users (email) VALUES ($1)
-         ON CONFLICT (email) DO UPDATE SET last_updated = CURRENT_TIMESTAMP",
+    // Claim mappings apply alike whichever provider the user signed in with
+    if let Some(name) = claim_mapping::display_name(&state.settings, &profile) {
+        profile.display_name = Some(name);
+    }
+
+    // Denylist and policy webhook, before anything is written for the login
+    login_policy::check(&state.ctx, &state.settings, &profile, context.ip).await?;
+
+    // Returning users are found by their provider identity first, so a changed
+    // Google email or Twitter handle still reaches the same account
+    let linked_user: Option<(i32,)> = sqlx::query_as(
+        "SELECT user_id FROM user_identities WHERE provider = $1 AND provider_user_id = $2",
     )
-    .bind(&email)
-    .execute(&state.db)
+    .bind(profile.provider.as_str())
+    .bind(&profile.subject)
+    .fetch_optional(&state.db)
     .await?;
 
-    // Store session in database
-    sqlx::query(
-        "INSERT INTO sessions (user_id, session_id, expires_at) VALUES (
-            (SELECT id FROM users WHERE email = $1 LIMIT 1),
-            $2, $3
+    // Unknown users only get an account when registration allows it
+    if linked_user.is_none()
+        && !registration::may_register(&state.db, &state.settings, &profile.email).await?
+    {
+        // Closed deployments take no requests; admins create accounts themselves
+        if state.settings.registration_mode == RegistrationMode::Closed {
+            tracing::info!("No account for {}, registration is closed", profile.email);
+            return Err(ApiError::AccountNotFound(profile.email));
+        }
+        registration::request_access(&state.db, &profile).await?;
+        return Err(ApiError::AccessRequested(profile.email));
+    }
+
+    // User, identity and session are written together so a failure can't leave
+    // a user without an identity or a session pointing at half-written rows
+    let mut tx = state.db.begin().await?;
+
+    // Store user in database. Soft-deleted users aren't updated, so no row comes back.
+    let user: Option<(i32, bool, Vec<String>, bool)> = match linked_user {
+        Some((user_id,)) => {
+            sqlx::query_as(
+                "UPDATE users SET
+                    display_name = COALESCE($2, display_name),
+                    last_updated = CURRENT_TIMESTAMP,
+                    last_login_at = NOW(),
+                    last_login_provider = $3
+                 WHERE id = $1 AND deleted_at IS NULL
+                 RETURNING id, FALSE AS inserted, roles,
+                           COALESCE(login_blocked_until > NOW(), FALSE) AS blocked",
+            )
+            .bind(user_id)
+            .bind(&profile.display_name)
+            .bind(profile.provider.as_str())
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+        // No identity yet: an account with the same email gets this provider linked
+        None => {
+            sqlx::query_as(
+                "INSERT INTO users (email, display_name, last_login_at, last_login_provider, onboarding_step)
+                 VALUES ($1, $2, NOW(), $3, 'profile')
+                 ON CONFLICT (email) DO UPDATE SET
+                    display_name = COALESCE(excluded.display_name, users.display_name),
+                    last_updated = CURRENT_TIMESTAMP,
+                    last_login_at = excluded.last_login_at,
+                    last_login_provider = excluded.last_login_provider
+                 WHERE users.deleted_at IS NULL
+                 RETURNING id, (xmax = 0) AS inserted, roles,
+                           COALESCE(login_blocked_until > NOW(), FALSE) AS blocked",
+            )
+            .bind(&profile.email)
+            .bind(&profile.display_name)
+            .bind(profile.provider.as_str())
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+    };
+
+    let Some((user_id, signup, mut roles, blocked)) = user else {
+        return Err(ApiError::LoginRejected(
+            "This account has been deleted.".to_string(),
+        ));
+    };
+
+    // Dropping the transaction undoes the login timestamps written above
+    if blocked {
+        return Err(ApiError::LoginRejected(
+            "Sign-in to this account is temporarily blocked. Please contact your administrator."
+                .to_string(),
+        ));
+    }
+
+    if signup {
+        registration::accept_invitation(&mut tx, &profile.email).await?;
+
+        let event = AuthEvent::UserCreated {
+            provider: profile.provider,
+        };
+        auth_events::record(&mut *tx, user_id, event).await?;
+
+        // A separate update so the role trigger records the grant
+        if state
+            .settings
+            .admin_emails
+            .contains(&profile.email.to_lowercase())
+        {
+            sqlx::query("UPDATE users SET roles = array_append(roles, 'admin') WHERE id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            roles.push("admin".to_string());
+            tracing::warn!("Granted admin role to user {} from ADMIN_EMAILS", user_id);
+        }
+    }
+
+    // Mapped roles are added on every login but never taken away, so roles
+    // granted by hand survive a change in the provider's claims
+    let mapped_roles: Vec<String> = claim_mapping::roles(&state.settings, &profile)
+        .into_iter()
+        .filter(|role| !roles.contains(role))
+        .collect();
+    if !mapped_roles.is_empty() {
+        sqlx::query("UPDATE users SET roles = roles || $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(&mapped_roles)
+            .execute(&mut *tx)
+            .await?;
+        tracing::warn!(
+            "Granted roles {:?} to user {} from CLAIM_ROLE_MAPPINGS",
+            mapped_roles,
+            user_id
+        );
+        roles.extend(mapped_roles);
+    }
+
+    // Link the provider identity to the user
+    let (identity_id, linked): (i32, bool) = sqlx::query_as(
+        "INSERT INTO user_identities (user_id, provider, provider_user_id) VALUES ($1, $2, $3)
+         ON CONFLICT (provider, provider_user_id) DO UPDATE SET last_login_at = NOW()
+         RETURNING id, (xmax = 0) AS inserted",
+    )
+    .bind(user_id)
+    .bind(profile.provider.as_str())
+    .bind(&profile.subject)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if linked {
+        let event = AuthEvent::IdentityLinked {
+            provider: profile.provider,
+            subject: &profile.subject,
+        };
+        auth_events::record(&mut *tx, user_id, event).await?;
+    }
+
+    if let Some(token) = token {
+        store_identity_tokens(&mut *tx, user_id, &profile, token).await?;
+    }
+
+    let new_device = track_device(&mut tx, user_id, &context).await?;
+
+    // Resolve the login location and compare it with the previous session
+    let location = state
+        .geoip
+        .as_ref()
+        .and_then(|geoip| geoip.lookup(context.ip))
+        .unwrap_or_default();
+
+    let impossible_travel = detect_impossible_travel(
+        &mut *tx,
+        user_id,
+        &location,
+        state.settings.max_travel_speed_kmh,
+    )
+    .await?;
+
+    if impossible_travel {
+        tracing::warn!(
+            "Impossible travel detected for {} logging in from {} ({:?}, {:?})",
+            profile.email,
+            context.ip,
+            location.city,
+            location.country
+        );
+        state.alerts.raise(
+            AlertKind::ImpossibleTravel,
+            format!(
+                "Impossible travel for {}: signed in from {} ({} {}) faster than {} km/h",
+                profile.email,
+                context.ip,
+                location.city.as_deref().unwrap_or("unknown city"),
+                location.country.as_deref().unwrap_or("unknown country"),
+                state.settings.max_travel_speed_kmh
+            ),
+        );
+    }
+
+    let fingerprint = session_fingerprint(&state.settings, &context);
+    let csrf_token = CsrfToken::new_random().secret().clone();
+
+    // Store session in database; every login gets its own row, so signing in on
+    // another device leaves existing sessions alone
+    let (session_row_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO sessions (
+            user_id, session_id, expires_at, ip_address, user_agent,
+            country, city, latitude, longitude, impossible_travel, fingerprint, csrf_token
         )
-        ON CONFLICT (user_id) DO UPDATE SET
-            session_id = excluded.session_id,
-            expires_at = excluded.expires_at",
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id",
     )
-    .bind(&email)
+    .bind(user_id)
     .bind(&session_id)
     .bind(max_age)
-    .execute(&state.db)
+    .bind(context.ip.to_string())
+    .bind(&context.user_agent)
+    .bind(&location.country)
+    .bind(&location.city)
+    .bind(location.latitude)
+    .bind(location.longitude)
+    .bind(impossible_travel)
+    .bind(&fingerprint)
+    .bind(&csrf_token)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let event = AuthEvent::SessionIssued {
+        provider: profile.provider,
+        ip: context.ip,
+    };
+    auth_events::record(&mut *tx, user_id, event).await?;
+
+    // A guest signing in keeps what they did as a guest
+    let guest_cookie = jar
+        .get(&guest_cookie_name(&state.settings))
+        .map(|cookie| cookie.value().to_owned());
+    if let Some(guest_session_id) = &guest_cookie {
+        if preferences::upgrade_guest(&mut tx, guest_session_id, user_id).await? {
+            tracing::debug!("Upgraded guest session to user {}", user_id);
+        }
+    }
+
+    tx.commit().await?;
+
+    tracing::debug!(
+        "Stored session {} for user {} with identity {}",
+        session_row_id,
+        user_id,
+        identity_id
+    );
+
+    // Counters aren't part of the login itself and would otherwise hold the daily
+    // stats row locked for the whole transaction
+    analytics::record_login(&state.db, profile.provider, signup).await;
+
+    // Alert the user when they sign in from somewhere new
+    if new_device {
+        notify_new_device(&profile, &context);
+    }
+
+    // Embedder hooks only ever see committed logins
+    if !state.post_login_hooks.is_empty() {
+        match UserProfile::load(&state.db, &session_id, fingerprint.as_deref()).await {
+            Ok(Some(user)) => {
+                let login = CompletedLogin {
+                    user: &user,
+                    identity_id,
+                    identity: &profile,
+                    token,
+                    signup,
+                };
+                post_login::run_hooks(state, &login).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!(
+                "Failed to load user {} for post-login hooks: {}",
+                user_id,
+                e
+            ),
+        }
+    }
+
+    let cookie = tracing::debug_span!("issue_cookie").in_scope(|| {
+        let value = match state.settings.session_mode {
+            SessionMode::Database => session_id,
+            SessionMode::Stateless => SessionClaims {
+                sid: session_id,
+                uid: user_id,
+                roles: roles.clone(),
+                exp: Utc::now().timestamp() + secs,
+                csrf_token,
+                fingerprint,
+            }
+            .encode(),
+        };
+
+        // Create secure cookie with expiration
+        session_cookie(&state.settings, value, TimeDuration::seconds(secs))
+    });
+
+    let jar = match guest_cookie {
+        Some(_) => jar.add(guest_removal_cookie(&state.settings)),
+        None => jar,
+    };
+    let jar = jar.add(last_provider_cookie(&state.settings, profile.provider));
+
+    Ok((
+        jar.add(cookie),
+        Redirect::to(login_redirect(&state.settings, &roles)),
+    ))
+}
+
+// Landing page after login: the first configured role the user has, or the default
+fn login_redirect<'a>(settings: &'a Settings, roles: &[String]) -> &'a str {
+    settings
+        .role_login_redirects
+        .iter()
+        .find(|(role, _)| roles.contains(role))
+        .map_or(&settings.login_redirect, |(_, path)| path)
+}
+
+// Only local paths and allowlisted URLs or origins are followed after logout,
+// so the parameter can't be used as an open redirect
+fn logout_redirect(settings: &Settings, requested: Option<&str>) -> String {
+    let Some(target) = requested else {
+        return settings.logout_redirect.clone();
+    };
+
+    if is_local_path(target) {
+        return target.to_string();
+    }
+
+    // Absolute URLs are sent re-serialized, so they are percent-encoded ASCII
+    let allowed = Url::parse(target).ok().filter(|url| {
+        let origin = url.origin().ascii_serialization();
+        let target = target.trim_end_matches('/');
+
+        settings
+            .logout_redirect_allowlist
+            .iter()
+            .any(|entry| *entry == origin || entry == target)
+    });
+
+    match allowed {
+        Some(url) => url.to_string(),
+        None => {
+            tracing::debug!("Ignoring logout redirect to {}", target);
+            settings.logout_redirect.clone()
+        }
+    }
+}
+
+// Save the provider tokens on the user's identity, merging newly granted scopes
+// into the existing ones. Returns false when the identity isn't linked to the user.
+#[tracing::instrument(level = "debug", skip_all, fields(user_id = user_id))]
+pub async fn store_identity_tokens(
+    db: impl PgExecutor<'_>,
+    user_id: i32,
+    profile: &ProviderProfile,
+    token: &BasicTokenResponse,
+) -> Result<bool, sqlx::Error> {
+    let expires_at = token
+        .expires_in()
+        .map(|d| Utc::now() + Duration::seconds(d.as_secs() as i64));
+
+    let scopes: Vec<String> = token
+        .scopes()
+        .map(|scopes| scopes.iter().map(|scope| scope.to_string()).collect())
+        .unwrap_or_default();
+
+    // Providers usually only return a refresh token on first consent, so keep the old one
+    let updated = sqlx::query(
+        "UPDATE user_identities SET
+            access_token = $4,
+            refresh_token = COALESCE($5, refresh_token),
+            token_expires_at = $6,
+            scopes = ARRAY(SELECT DISTINCT unnest(scopes || $7::text[]))
+         WHERE user_id = $1 AND provider = $2 AND provider_user_id = $3",
+    )
+    .bind(user_id)
+    .bind(profile.provider.as_str())
+    .bind(&profile.subject)
+    .bind(token.access_token().secret())
+    .bind(token.refresh_token().map(|token| token.secret()))
+    .bind(expires_at)
+    .bind(&scopes)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(updated > 0)
+}
+
+// Record the device used for a login. Returns true when it is new for a user
+// who has signed in before, so first logins don't trigger an alert.
+#[tracing::instrument(level = "debug", skip_all, fields(user_id = user_id))]
+async fn track_device(
+    db: &mut PgConnection,
+    user_id: i32,
+    context: &LoginContext,
+) -> Result<bool, sqlx::Error> {
+    let seen = sqlx::query(
+        "UPDATE user_devices SET last_seen_at = NOW()
+         WHERE user_id = $1 AND ip_address = $2 AND user_agent = $3",
+    )
+    .bind(user_id)
+    .bind(context.ip.to_string())
+    .bind(&context.user_agent)
+    .execute(&mut *db)
+    .await?
+    .rows_affected();
+
+    if seen > 0 {
+        return Ok(false);
+    }
+
+    let (known_devices,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM user_devices WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&mut *db)
+            .await?;
+
+    sqlx::query(
+        "INSERT INTO user_devices (user_id, ip_address, user_agent) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id, ip_address, user_agent) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(context.ip.to_string())
+    .bind(&context.user_agent)
+    .execute(&mut *db)
+    .await?;
+
+    Ok(known_devices > 0)
+}
+
+// Compare the login location with the user's most recent session
+#[tracing::instrument(level = "debug", skip_all, fields(user_id = user_id))]
+async fn detect_impossible_travel(
+    db: impl PgExecutor<'_>,
+    user_id: i32,
+    location: &GeoLocation,
+    max_speed_kmh: f64,
+) -> Result<bool, sqlx::Error> {
+    let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) else {
+        return Ok(false);
+    };
+
+    let previous: Option<(Option<f64>, Option<f64>, Option<DateTime<Utc>>)> = sqlx::query_as(
+        "SELECT latitude, longitude, created_at FROM sessions
+         WHERE user_id = $1
+         ORDER BY created_at DESC NULLS LAST
+         LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(db)
     .await?;
 
-    Ok((jar.add(cookie), Redirect::to("/protected")))
+    let Some((Some(previous_latitude), Some(previous_longitude), Some(previous_at))) = previous
+    else {
+        return Ok(false);
+    };
+
+    let distance = geoip::distance_km(previous_latitude, previous_longitude, latitude, longitude);
+    // Treat near-simultaneous logins as one minute apart to avoid dividing by zero
+    let elapsed_hours = (Utc::now() - previous_at).num_seconds().max(60) as f64 / 3600.0;
+
+    Ok(geoip::is_impossible_travel(
+        distance,
+        elapsed_hours,
+        max_speed_kmh,
+    ))
+}
+
+// There is no mailer yet, so new sign-ins are reported through the security log
+fn notify_new_device(profile: &ProviderProfile, context: &LoginContext) {
+    tracing::warn!(
+        "New sign-in for {} via {} from {} ({})",
+        profile.email,
+        profile.provider.label(),
+        context.ip,
+        context.user_agent
+    );
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutForm {
+    pub csrf_token: String,
+    // End every session of the user, not just the current one
+    #[serde(default)]
+    pub everywhere: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutQuery {
+    // Where to go afterwards; see logout_redirect
+    pub redirect: Option<String>,
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
     jar: PrivateCookieJar,
+    Query(query): Query<LogoutQuery>,
+    Form(form): Form<LogoutForm>,
 ) -> Result<impl IntoResponse, ApiError> {
     // Get the session cookie to invalidate it in the database
-    if let Some(cookie) = jar.get("sid") {
-        let session_id = cookie.value();
+    let session_id = jar
+        .get(&state.settings.session_cookie_name)
+        .and_then(|cookie| stateless_session::session_id(&state.settings, cookie.value()));
+
+    if let Some(session_id) = session_id {
+        // The form must carry the token issued with this session
+        let expected: Option<(Option<String>,)> =
+            sqlx::query_as("SELECT csrf_token FROM sessions WHERE session_id = $1")
+                .bind(&session_id)
+                .fetch_optional(&state.db)
+                .await?;
+
+        if let Some((expected,)) = expected {
+            if expected.as_deref() != Some(form.csrf_token.as_str()) {
+                return Err(ApiError::Forbidden);
+            }
+        }
 
         // Remove session from database
-        sqlx::query("DELETE FROM sessions WHERE session_id = $1")
-            .bind(session_id)
-            .execute(&state.db)
+        let (query, reason) = if form.everywhere {
+            (
+                "DELETE FROM sessions WHERE user_id = (
+                    SELECT user_id FROM sessions WHERE session_id = $1
+                 )
+                 RETURNING user_id",
+                "logout_everywhere",
+            )
+        } else {
+            (
+                "DELETE FROM sessions WHERE session_id = $1 RETURNING user_id",
+                "logout",
+            )
+        };
+
+        let revoked: Vec<(i32,)> = sqlx::query_as(query)
+            .bind(&session_id)
+            .fetch_all(&state.db)
             .await?;
+
+        for (user_id,) in revoked {
+            let event = AuthEvent::SessionRevoked { reason };
+            auth_events::record(&state.db, user_id, event).await?;
+        }
     }
 
     // Remove the cookie
-    let removal_cookie = Cookie::build(("sid", ""))
-        .path("/")
-        .http_only(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Lax)
-        .max_age(TimeDuration::seconds(-1));
+    let removal_cookie = session_removal_cookie(&state.settings);
+
+    // Redirect::to panics on values that aren't valid header values
+    let redirect = logout_redirect(&state.settings, query.redirect.as_deref());
+    let location = HeaderValue::from_str(&redirect)
+        .or_else(|_| HeaderValue::from_str(&state.settings.logout_redirect))
+        .unwrap_or(HeaderValue::from_static("/"));
+
+    Ok((
+        StatusCode::SEE_OTHER,
+        jar.add(removal_cookie),
+        [(header::LOCATION, location)],
+    ))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionRenewal {
+    pub expires_at: DateTime<Utc>,
+    // False when the session isn't close enough to expiry yet, or has reached
+    // SESSION_MAX_LIFETIME_SECS; the expiry is unchanged then
+    pub renewed: bool,
+}
+
+// Let single-page apps keep a session alive without a new provider login. A session
+// close to expiry gets a new id and expiry and the cookie is reissued; the old id
+// stops working at once, in stateless mode through the revocation broadcast.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn refresh_session(
+    State(state): State<AppState>,
+    jar: PrivateCookieJar,
+    context: LoginContext,
+) -> Result<(PrivateCookieJar, Json<SessionRenewal>), ApiError> {
+    let session_id = jar
+        .get(&state.settings.session_cookie_name)
+        .and_then(|cookie| stateless_session::session_id(&state.settings, cookie.value()))
+        .filter(|session_id| !state.revoked_sessions.is_revoked(session_id))
+        .ok_or(ApiError::Unauthorized)?;
+
+    let fingerprint = session_fingerprint(&state.settings, &context);
+
+    let mut tx = state.db.begin().await?;
+
+    let session: Option<(
+        DateTime<Utc>,
+        Option<DateTime<Utc>>,
+        i32,
+        Vec<String>,
+        Option<String>,
+    )> = sqlx::query_as(
+        "SELECT sessions.expires_at, sessions.created_at, users.id, users.roles,
+                sessions.csrf_token
+         FROM sessions
+         JOIN users ON sessions.user_id = users.id
+         WHERE sessions.session_id = $1 AND sessions.expires_at > NOW()
+           AND users.deleted_at IS NULL
+           AND ($2::text IS NULL OR sessions.fingerprint = $2)
+         FOR UPDATE OF sessions",
+    )
+    .bind(&session_id)
+    .bind(fingerprint.as_deref())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((expires_at, created_at, user_id, roles, csrf_token)) = session else {
+        return Err(ApiError::Unauthorized);
+    };
+
+    let now = Utc::now();
+    let settings = &state.settings;
+    let mut renewed_until = now + Duration::seconds(settings.session_renewal_secs);
+    if let Some(created_at) = created_at {
+        renewed_until =
+            renewed_until.min(created_at + Duration::seconds(settings.session_max_lifetime_secs));
+    }
 
-    Ok((jar.add(removal_cookie), Redirect::to("/")))
+    let due = expires_at - now <= Duration::seconds(settings.session_renewal_window_secs);
+    if !due || renewed_until <= expires_at {
+        return Ok((
+            jar,
+            Json(SessionRenewal {
+                expires_at,
+                renewed: false,
+            }),
+        ));
+    }
+
+    let new_session_id = CsrfToken::new_random().secret().clone();
+    sqlx::query("UPDATE sessions SET session_id = $2, expires_at = $3 WHERE session_id = $1")
+        .bind(&session_id)
+        .bind(&new_session_id)
+        .bind(renewed_until)
+        .execute(&mut *tx)
+        .await?;
+
+    // The UPDATE fires no delete notification, so the old stateless cookie would
+    // otherwise stay valid on every route but the high-risk ones
+    if settings.session_mode == SessionMode::Stateless {
+        invalidation::broadcast_session_revoked(&mut *tx, &session_id, expires_at).await?;
+    }
+    tx.commit().await?;
+
+    // Stateless claims take the current roles, so role changes made since login
+    // are picked up too
+    let value = match settings.session_mode {
+        SessionMode::Database => new_session_id,
+        SessionMode::Stateless => SessionClaims {
+            sid: new_session_id,
+            uid: user_id,
+            roles,
+            exp: renewed_until.timestamp(),
+            csrf_token: csrf_token.unwrap_or_default(),
+            fingerprint,
+        }
+        .encode(),
+    };
+
+    let max_age = TimeDuration::seconds((renewed_until - now).num_seconds());
+    let cookie = session_cookie(settings, value, max_age);
+
+    Ok((
+        jar.add(cookie),
+        Json(SessionRenewal {
+            expires_at: renewed_until,
+            renewed: true,
+        }),
+    ))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForcedLogout {
+    pub sessions_revoked: u64,
+    pub support_tokens_revoked: u64,
+    pub login_blocked_until: Option<DateTime<Utc>>,
+}
+
+// Incident response: end every session of the user and revoke the support tokens
+// they handed out, optionally keeping them from signing in again for a while.
+// Returns None when there is no such (undeleted) user.
+pub async fn force_logout(
+    db: &PgPool,
+    user_id: i32,
+    block_for: Option<Duration>,
+) -> Result<Option<ForcedLogout>, sqlx::Error> {
+    let login_blocked_until = block_for.map(|block_for| Utc::now() + block_for);
+
+    let mut tx = db.begin().await?;
+
+    // A new force-logout replaces any earlier block, so an admin can also lift it
+    let found = sqlx::query(
+        "UPDATE users SET login_blocked_until = $2 WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(login_blocked_until)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if found == 0 {
+        return Ok(None);
+    }
+
+    // Deleting the rows also revokes stateless sessions on every instance
+    let sessions_revoked = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let support_tokens_revoked = sqlx::query(
+        "UPDATE support_access_tokens SET revoked_at = NOW()
+         WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if sessions_revoked > 0 {
+        let event = AuthEvent::SessionRevoked {
+            reason: "admin_force_logout",
+        };
+        auth_events::record(&mut *tx, user_id, event).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(ForcedLogout {
+        sessions_revoked,
+        support_tokens_revoked,
+        login_blocked_until,
+    }))
+}
+
+// Delete expired sessions in small batches so large tables aren't locked for long
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn prune_expired_sessions(db: &PgPool, batch_size: i64) -> Result<u64, sqlx::Error> {
+    let mut total = 0;
+
+    loop {
+        let deleted = sqlx::query(
+            "DELETE FROM sessions WHERE id IN (
+                SELECT id FROM sessions WHERE expires_at <= NOW() LIMIT $1
+            )",
+        )
+        .bind(batch_size)
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        total += deleted;
+
+        if deleted < batch_size as u64 {
+            return Ok(total);
+        }
+    }
+}
+
+pub fn spawn_session_pruner(db: PgPool, interval: StdDuration, batch_size: i64) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match prune_expired_sessions(&db, batch_size).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Pruned {} expired sessions", count),
+                Err(e) => tracing::error!("Failed to prune expired sessions: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        let mut settings = Settings::from_env();
+        settings.logout_redirect = "/signed-out".to_string();
+        settings.logout_redirect_allowlist = vec![
+            "https://allowed.example.com".to_string(),
+            "https://partner.example.com/goodbye".to_string(),
+        ];
+        settings
+    }
+
+    #[test]
+    fn logout_redirect_defaults_without_a_target() {
+        assert_eq!(logout_redirect(&settings(), None), "/signed-out");
+    }
+
+    #[test]
+    fn logout_redirect_follows_local_paths() {
+        assert_eq!(
+            logout_redirect(&settings(), Some("/protected?tab=1")),
+            "/protected?tab=1"
+        );
+    }
+
+    #[test]
+    fn logout_redirect_rejects_other_hosts() {
+        let settings = settings();
+        for target in [
+            "//evil.com",
+            "/\\evil.com",
+            "https://evil.com",
+            "https://allowed.example.com.evil.com",
+            "https://allowed.example.com@evil.com",
+            "http://allowed.example.com",
+        ] {
+            assert_eq!(logout_redirect(&settings, Some(target)), "/signed-out");
+        }
+    }
+
+    #[test]
+    fn logout_redirect_rejects_header_injection() {
+        let settings = settings();
+        assert_eq!(
+            logout_redirect(&settings, Some("/ok\r\nSet-Cookie: a=b")),
+            "/signed-out"
+        );
+
+        // URLs are re-serialized, which drops the line break
+        let target = logout_redirect(&settings, Some("https://allowed.example.com/\r\nx"));
+        assert!(!target.contains(['\r', '\n']));
+    }
+
+    #[test]
+    fn logout_redirect_allows_any_path_on_an_allowlisted_origin() {
+        assert_eq!(
+            logout_redirect(&settings(), Some("https://allowed.example.com/bye")),
+            "https://allowed.example.com/bye"
+        );
+    }
+
+    #[test]
+    fn logout_redirect_allows_only_the_allowlisted_url() {
+        let settings = settings();
+        assert_eq!(
+            logout_redirect(&settings, Some("https://partner.example.com/goodbye")),
+            "https://partner.example.com/goodbye"
+        );
+        assert_eq!(
+            logout_redirect(&settings, Some("https://partner.example.com/goodbye/")),
+            "https://partner.example.com/goodbye/"
+        );
+        assert_eq!(
+            logout_redirect(&settings, Some("https://partner.example.com/other")),
+            "/signed-out"
+        );
+    }
 }
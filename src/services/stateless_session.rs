@@ -0,0 +1,47 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::config::settings::SessionMode;
+use crate::config::Settings;
+
+// Session carried in the private (encrypted and authenticated) cookie. Only what
+// authenticating a request needs is kept here, since browsers drop cookies over
+// 4 KB; the rest of the user is loaded by id when a handler asks for it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionClaims {
+    // Id of the matching sessions row, checked on high-risk routes
+    pub sid: String,
+    pub uid: i32,
+    // Roles at login or the last renewal
+    pub roles: Vec<String>,
+    // Expiry as a Unix timestamp
+    pub exp: i64,
+    pub csrf_token: String,
+    pub fingerprint: Option<String>,
+}
+
+impl SessionClaims {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("session claims serialize")
+    }
+
+    // Parse a cookie value, rejecting expired sessions
+    pub fn decode(value: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(value)
+            .ok()
+            .filter(|claims| claims.exp > Utc::now().timestamp())
+    }
+
+    // Same rule as the database check: only compared when binding is enabled
+    pub fn matches(&self, fingerprint: Option<&str>) -> bool {
+        fingerprint.is_none() || self.fingerprint.as_deref() == fingerprint
+    }
+}
+
+// Session id held by a session cookie in either mode
+pub fn session_id(settings: &Settings, value: &str) -> Option<String> {
+    match settings.session_mode {
+        SessionMode::Database => Some(value.to_string()),
+        SessionMode::Stateless => SessionClaims::decode(value).map(|claims| claims.sid),
+    }
+}
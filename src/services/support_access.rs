@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, Utc};
+use oauth2::CsrfToken;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use std::net::IpAddr;
+
+// Parts of an account a support token can expose
+pub const SUPPORT_SCOPES: [&str; 3] = ["profile", "identities", "sessions"];
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SupportToken {
+    pub id: i32,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// A token that was successfully redeemed by an agent
+#[derive(Debug, FromRow)]
+pub struct SupportGrant {
+    pub id: i32,
+    pub user_id: i32,
+    pub scopes: Vec<String>,
+}
+
+impl SupportGrant {
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+// Only the hash is stored, so a database leak doesn't hand out account access
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// Create a token and return its secret; it is shown to the user exactly once
+pub async fn create_token(
+    db: &PgPool,
+    user_id: i32,
+    scopes: &[String],
+    lifetime: Duration,
+) -> Result<String, sqlx::Error> {
+    let token = CsrfToken::new_random().secret().clone();
+
+    sqlx::query(
+        "INSERT INTO support_access_tokens (user_id, token_hash, scopes, expires_at)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(scopes)
+    .bind(Utc::now() + lifetime)
+    .execute(db)
+    .await?;
+
+    Ok(token)
+}
+
+pub async fn active_tokens(db: &PgPool, user_id: i32) -> Result<Vec<SupportToken>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, scopes, created_at, expires_at FROM support_access_tokens
+         WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+         ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn revoke_token(db: &PgPool, user_id: i32, token_id: i32) -> Result<bool, sqlx::Error> {
+    let revoked = sqlx::query(
+        "UPDATE support_access_tokens SET revoked_at = NOW()
+         WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(token_id)
+    .bind(user_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(revoked > 0)
+}
+
+// Look up a live token and record the access in the audit log
+pub async fn redeem_token(
+    db: &PgPool,
+    token: &str,
+    agent_user_id: i32,
+    ip: IpAddr,
+) -> Result<Option<SupportGrant>, sqlx::Error> {
+    let grant: Option<SupportGrant> = sqlx::query_as(
+        "SELECT id, user_id, scopes FROM support_access_tokens
+         WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(grant) = &grant {
+        sqlx::query(
+            "INSERT INTO support_access_log (token_id, agent_user_id, ip_address)
+             VALUES ($1, $2, $3)",
+        )
+        .bind(grant.id)
+        .bind(agent_user_id)
+        .bind(ip.to_string())
+        .execute(db)
+        .await?;
+
+        tracing::info!(
+            "Support agent {} accessed user {} with token {} from {}",
+            agent_user_id,
+            grant.user_id,
+            grant.id,
+            ip
+        );
+    }
+
+    Ok(grant)
+}
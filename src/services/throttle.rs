@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::errors::ApiError;
+use crate::services::alerts::{AlertKind, Alerts};
+
+#[derive(Clone, Copy)]
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+}
+
+// Tracks failed authentication attempts per identifier (IP address or email)
+// and applies an exponentially growing delay once the free attempts are used up.
+// The counts live in this process only: replicas don't share them, and a restart
+// forgets them.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    attempts: Arc<Mutex<HashMap<String, Attempts>>>,
+    // Replaced when the configuration is reloaded
+    limits: Arc<RwLock<ThrottleLimits>>,
+    alerts: Alerts,
+}
+
+#[derive(Clone, Copy)]
+struct ThrottleLimits {
+    free_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+pub fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+pub fn email_key(email: &str) -> String {
+    format!("email:{}", email.to_lowercase())
+}
+
+impl LoginThrottle {
+    pub fn new(
+        alerts: Alerts,
+        free_attempts: u32,
+        base_delay: Duration,
+        max_delay: Duration,
+    ) -> Self {
+        Self {
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+            limits: Arc::new(RwLock::new(ThrottleLimits {
+                free_attempts,
+                base_delay,
+                max_delay,
+            })),
+            alerts,
+        }
+    }
+
+    // Failure counts are kept; only the delays derived from them change
+    pub fn set_limits(&self, free_attempts: u32, base_delay: Duration, max_delay: Duration) {
+        *self.limits.write().unwrap() = ThrottleLimits {
+            free_attempts,
+            base_delay,
+            max_delay,
+        };
+    }
+
+    fn limits(&self) -> ThrottleLimits {
+        *self.limits.read().unwrap()
+    }
+
+    // Reject the attempt if the identifier is still inside its back-off window
+    pub async fn check(&self, key: &str) -> Result<(), ApiError> {
+        let attempts = self.attempts.lock().await;
+
+        let Some(entry) = attempts.get(key) else {
+            return Ok(());
+        };
+
+        let delay = self.delay_for(entry.failures);
+        let elapsed = entry.last_failure.elapsed();
+
+        if elapsed < delay {
+            return Err(ApiError::TooManyRequests(delay - elapsed));
+        }
+
+        Ok(())
+    }
+
+    pub async fn record_failure(&self, key: &str) {
+        let mut attempts = self.attempts.lock().await;
+
+        // Forget identifiers that have been quiet for longer than the maximum delay
+        let limits = self.limits();
+        let max_delay = limits.max_delay;
+        attempts.retain(|_, entry| entry.last_failure.elapsed() < max_delay);
+
+        let entry = attempts.entry(key.to_string()).or_insert(Attempts {
+            failures: 0,
+            last_failure: Instant::now(),
+        });
+        entry.failures += 1;
+        entry.last_failure = Instant::now();
+
+        if entry.failures > limits.free_attempts {
+            tracing::warn!(
+                "Throttling {} after {} failed authentication attempts",
+                key,
+                entry.failures
+            );
+        }
+
+        // Alert once, when an identifier is first throttled
+        if entry.failures == limits.free_attempts + 1 {
+            self.alerts.raise(
+                AlertKind::FailedLogins,
+                format!(
+                    "Repeated failed logins for {}: {} attempts, now throttled",
+                    key, entry.failures
+                ),
+            );
+        }
+    }
+
+    // Meant for account keys; IP failures are left to age out so one good login
+    // doesn't wipe a client's history
+    pub async fn record_success(&self, key: &str) {
+        self.attempts.lock().await.remove(key);
+    }
+
+    fn delay_for(&self, failures: u32) -> Duration {
+        let limits = self.limits();
+        if failures <= limits.free_attempts {
+            return Duration::ZERO;
+        }
+
+        let exponent = (failures - limits.free_attempts - 1).min(16);
+        limits
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(limits.max_delay)
+    }
+}
+
+// Fixed-window request limit per IP address, for unauthenticated endpoints that
+// write to the database
+#[derive(Clone)]
+pub struct RateLimiter {
+    windows: Arc<Mutex<HashMap<IpAddr, (Instant, u32)>>>,
+    limit: Arc<AtomicU32>,
+    window: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            windows: Arc::new(Mutex::new(HashMap::new())),
+            limit: Arc::new(AtomicU32::new(limit)),
+            window,
+        }
+    }
+
+    pub fn set_limit(&self, limit: u32) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    pub async fn check(&self, ip: IpAddr) -> Result<(), ApiError> {
+        let mut windows = self.windows.lock().await;
+
+        let window = self.window;
+        windows.retain(|_, (started, _)| started.elapsed() < window);
+
+        let (started, count) = windows.entry(ip).or_insert((Instant::now(), 0));
+        if *count >= self.limit.load(Ordering::Relaxed) {
+            return Err(ApiError::TooManyRequests(window - started.elapsed()));
+        }
+        *count += 1;
+
+        Ok(())
+    }
+}
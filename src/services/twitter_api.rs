@@ -0,0 +1,71 @@
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ApiError;
+
+const TWITTER_API_URL: &str = "https://api.twitter.com/2";
+
+#[derive(Debug, Deserialize)]
+pub struct Tweet {
+    pub id: String,
+    pub text: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TweetList {
+    // Absent when the user has no tweets
+    #[serde(default)]
+    data: Vec<Tweet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedTweet {
+    data: Tweet,
+}
+
+#[derive(Serialize)]
+struct NewTweet<'a> {
+    text: &'a str,
+}
+
+// Most recent tweets of a user; needs the tweet.read and users.read scopes
+pub async fn recent_tweets(
+    ctx: &ReqwestClient,
+    access_token: &str,
+    twitter_user_id: &str,
+) -> Result<Vec<Tweet>, ApiError> {
+    let tweets = ctx
+        .get(format!(
+            "{}/users/{}/tweets",
+            TWITTER_API_URL, twitter_user_id
+        ))
+        .query(&[("max_results", "10"), ("tweet.fields", "created_at")])
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TweetList>()
+        .await?;
+
+    Ok(tweets.data)
+}
+
+// Post a tweet on the user's behalf; needs the tweet.write scope
+pub async fn post_tweet(
+    ctx: &ReqwestClient,
+    access_token: &str,
+    text: &str,
+) -> Result<Tweet, ApiError> {
+    let tweet = ctx
+        .post(format!("{}/tweets", TWITTER_API_URL))
+        .json(&NewTweet { text })
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<CreatedTweet>()
+        .await?;
+
+    Ok(tweet.data)
+}
@@ -0,0 +1,268 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::services::auth_events::{self, AuthEvent};
+use crate::services::registration;
+
+// One user to create, from a JSON array or a CSV row
+#[derive(Debug, Deserialize)]
+pub struct ImportRow {
+    pub email: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub org: Option<String>,
+}
+
+// Split CSV text into records, honouring quoted fields with "" escapes and line breaks
+fn csv_records(body: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // Blank lines carry no user
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+// Parse CSV with a header row naming the columns: email is required, name, roles
+// (separated by ;) and org are optional
+pub fn parse_csv(body: &str) -> Result<Vec<ImportRow>, String> {
+    let mut records = csv_records(body).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or("The CSV is empty")?
+        .into_iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let email = column("email").ok_or("The CSV header has no email column")?;
+    let (name, roles, org) = (column("name"), column("roles"), column("org"));
+
+    let field = |record: &[String], index: Option<usize>| {
+        index
+            .and_then(|index| record.get(index))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    Ok(records
+        .map(|record| ImportRow {
+            email: field(&record, Some(email)).unwrap_or_default(),
+            name: field(&record, name),
+            roles: field(&record, roles)
+                .map(|roles| {
+                    roles
+                        .split(';')
+                        .map(|role| role.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            org: field(&record, org),
+        })
+        .collect())
+}
+
+// Queue an import and process it in the background; returns the job id to poll
+pub async fn start_import(
+    db: &PgPool,
+    rows: Vec<ImportRow>,
+    created_by: i32,
+) -> Result<i32, sqlx::Error> {
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO user_imports (created_by, total_rows) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(created_by)
+    .bind(rows.len() as i32)
+    .fetch_one(db)
+    .await?;
+
+    let db = db.clone();
+    tokio::spawn(async move {
+        let status = match run_import(&db, id, rows).await {
+            Ok(()) => "finished",
+            Err(e) => {
+                tracing::error!("User import {} failed: {}", id, e);
+                "failed"
+            }
+        };
+
+        if let Err(e) =
+            sqlx::query("UPDATE user_imports SET status = $2, finished_at = NOW() WHERE id = $1")
+                .bind(id)
+                .bind(status)
+                .execute(&db)
+                .await
+        {
+            tracing::error!("Failed to finish user import {}: {}", id, e);
+        }
+    });
+
+    Ok(id)
+}
+
+async fn run_import(db: &PgPool, id: i32, rows: Vec<ImportRow>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE user_imports SET status = 'running' WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+
+    for (index, row) in rows.iter().enumerate() {
+        let column = match import_row(db, id, row).await {
+            Ok(()) => "created_rows",
+            Err(error) => {
+                sqlx::query(
+                    "INSERT INTO user_import_errors (import_id, row_number, email, error)
+                     VALUES ($1, $2, $3, $4)",
+                )
+                .bind(id)
+                .bind(index as i32 + 1)
+                .bind(&row.email)
+                .bind(error)
+                .execute(db)
+                .await?;
+                "failed_rows"
+            }
+        };
+
+        // Counters are kept current so the job can be polled while it runs
+        sqlx::query(&format!(
+            "UPDATE user_imports SET {column} = {column} + 1 WHERE id = $1"
+        ))
+        .bind(id)
+        .execute(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Create one user, or explain why the row was rejected
+async fn import_row(db: &PgPool, import_id: i32, row: &ImportRow) -> Result<(), String> {
+    let email = row.email.trim().to_lowercase();
+    if !email.contains('@') {
+        return Err("Invalid email address".to_string());
+    }
+
+    if row.org.is_some() {
+        return Err("Organizations are not supported".to_string());
+    }
+
+    if row.roles.iter().any(|role| role.is_empty()) {
+        return Err("Roles must not be empty".to_string());
+    }
+
+    match insert_user(db, import_id, &email, row).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("A user with this email already exists".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Returns false when the email is already taken
+async fn insert_user(
+    db: &PgPool,
+    import_id: i32,
+    email: &str,
+    row: &ImportRow,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let user: Option<(i32,)> = sqlx::query_as(
+        "INSERT INTO users (email, display_name, onboarding_step) VALUES ($1, $2, 'profile')
+         ON CONFLICT (email) DO NOTHING
+         RETURNING id",
+    )
+    .bind(email)
+    .bind(&row.name)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((user_id,)) = user else {
+        return Ok(false);
+    };
+
+    // A separate update so the role trigger records the grants
+    if !row.roles.is_empty() {
+        sqlx::query("UPDATE users SET roles = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(&row.roles)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    registration::accept_invitation(&mut tx, email).await?;
+    auth_events::record(&mut *tx, user_id, AuthEvent::UserImported { import_id }).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct UserImport {
+    pub id: i32,
+    pub created_by: Option<i32>,
+    pub status: String,
+    pub total_rows: i32,
+    pub created_rows: i32,
+    pub failed_rows: i32,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ImportError {
+    pub row_number: i32,
+    pub email: Option<String>,
+    pub error: String,
+}
+
+pub async fn find_import(db: &PgPool, id: i32) -> Result<Option<UserImport>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, created_by, status, total_rows, created_rows, failed_rows, created_at,
+                finished_at
+         FROM user_imports WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn import_errors(db: &PgPool, id: i32) -> Result<Vec<ImportError>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT row_number, email, error FROM user_import_errors
+         WHERE import_id = $1 ORDER BY row_number",
+    )
+    .bind(id)
+    .fetch_all(db)
+    .await
+}
@@ -2,12 +2,36 @@ use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
 use reqwest::Client as ReqwestClient;
 use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::config::reload::LiveConfig;
+use crate::config::Settings;
+use crate::oauth::GoogleKeys;
+use crate::services::pool_metrics::PoolMetrics;
+use crate::services::post_login::PostLoginHooks;
+use crate::services::{Alerts, GeoIp, LoginThrottle, Maintenance, RateLimiter, RevokedSessions};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub ctx: ReqwestClient,
     pub key: Key, // TODO may want to make this private; add handler
+    pub settings: Arc<Settings>,
+    pub throttle: LoginThrottle,
+    pub geoip: Option<Arc<GeoIp>>,
+    pub google_keys: GoogleKeys,
+    pub maintenance: Maintenance,
+    // Per-IP limit on browser CSP violation reports
+    pub csp_report_limiter: RateLimiter,
+    // CORS origins and the log filter, replaced on configuration reload
+    pub live_config: LiveConfig,
+    // Webhook notifications for security events
+    pub alerts: Alerts,
+    // Stateless sessions deleted on any instance, filled from Postgres notifications
+    pub revoked_sessions: RevokedSessions,
+    pub pool_metrics: PoolMetrics,
+    // Set by init_router; run after every successful login
+    pub post_login_hooks: PostLoginHooks,
 }
 
 impl FromRef<AppState> for Key {
@@ -0,0 +1,35 @@
+// Renew the Google session in a hidden iframe. Resolves with "ok" when a fresh
+// session cookie was set, or with Google's error (e.g. "login_required") when the
+// user has to go through the interactive login instead.
+function silentAuth(timeoutMs = 10000) {
+    return new Promise((resolve) => {
+        const frame = document.createElement("iframe");
+        frame.hidden = true;
+        frame.src = "/api/auth/google_silent";
+
+        const finish = (status) => {
+            window.removeEventListener("message", onMessage);
+            clearTimeout(timer);
+            frame.remove();
+            resolve(status);
+        };
+
+        const onMessage = (event) => {
+            if (event.origin !== window.location.origin || event.data?.type !== "silent_auth") {
+                return;
+            }
+            finish(event.data.status);
+        };
+
+        const timer = setTimeout(() => finish("timeout"), timeoutMs);
+        window.addEventListener("message", onMessage);
+        document.body.appendChild(frame);
+    });
+}
+
+// Fall back to the full redirect flow when silent renewal is not possible
+async function renewSession() {
+    if ((await silentAuth()) !== "ok") {
+        window.location.href = "/api/auth/google_login";
+    }
+}